@@ -0,0 +1,270 @@
+use std::{sync::Arc, time::Duration};
+
+use log::{error, info, warn};
+use sqlx::PgPool;
+use starknet::{
+    accounts::{Call, SingleOwnerAccount},
+    providers::jsonrpc::{HttpTransport, JsonRpcClient},
+    signers::LocalWallet,
+};
+use tokio::{
+    sync::{Mutex, Notify},
+    task,
+    time::timeout,
+};
+
+use crate::{
+    config::RetryConfig,
+    metrics::{SUBMISSION_FAILURES, SUBMISSION_RETRIES},
+    nonce::manager::NonceManager,
+    supervisor::in_flight::{InFlight, InFlightGuard},
+    tracker::{
+        process::{spawn_tracking, track_execution},
+        receipt::ExecutionStatus,
+        store::record_failure,
+    },
+};
+
+use super::retry::RetryQueue;
+
+// A call ready to be executed for a user action.
+// @action_key: The key of the order, deposit or withdrawal.
+// @action_type: The kind of action executed (order, deposit, ...).
+// @call: The execute call built by the matching handler.
+// @execution_fee: The fee paid by the user for the execution, None for liquidations.
+#[derive(Debug, Clone)]
+pub struct PendingCall {
+    pub action_key: String,
+    pub action_type: &'static str,
+    pub call: Call,
+    pub execution_fee: Option<u128>,
+}
+
+// Collects ready execute calls and sends them together as one multicall.
+// A batch is flushed once it holds max_batch_size calls or max_batch_delay elapsed.
+// When the batch cannot be sent or reverts, its calls are retried one by one so a
+// single failing action does not block the others.
+// A call whose submission fails with a transient error is sent again after a backoff,
+// and marked as failed once it used all its attempts.
+pub struct BatchExecutor {
+    nonce_manager: Arc<NonceManager>,
+    pool: PgPool,
+    queue: Mutex<Vec<PendingCall>>,
+    flush_notify: Notify,
+    max_batch_size: usize,
+    max_batch_delay: Duration,
+    // Counts the batches being submitted, so a shutdown can wait for them.
+    in_flight: Arc<InFlight>,
+    retries: RetryQueue,
+}
+
+impl BatchExecutor {
+    pub fn new(
+        nonce_manager: Arc<NonceManager>,
+        pool: PgPool,
+        max_batch_size: usize,
+        max_batch_delay: Duration,
+        in_flight: Arc<InFlight>,
+        retry: RetryConfig,
+    ) -> Self {
+        BatchExecutor {
+            nonce_manager,
+            pool,
+            queue: Mutex::new(Vec::new()),
+            flush_notify: Notify::new(),
+            max_batch_size: max_batch_size.max(1),
+            max_batch_delay,
+            in_flight,
+            retries: RetryQueue::new(retry),
+        }
+    }
+
+    // The keeper account, used by the handlers to build their calls.
+    pub fn account(&self) -> Arc<SingleOwnerAccount<JsonRpcClient<HttpTransport>, LocalWallet>> {
+        self.nonce_manager.account()
+    }
+
+    // Queues a call for the next batch.
+    pub async fn push(&self, pending_call: PendingCall) {
+        let mut queue = self.queue.lock().await;
+        queue.push(pending_call);
+        if queue.len() >= self.max_batch_size {
+            self.flush_notify.notify_one();
+        }
+    }
+
+    // Drops the queued and retried calls of an action, e.g. once it is updated or cancelled.
+    // Returns the number of calls removed.
+    pub async fn remove(&self, action_key: &str) -> usize {
+        let mut queue = self.queue.lock().await;
+        let queued = queue.len();
+        queue.retain(|pending| pending.action_key != action_key);
+        queued - queue.len() + self.retries.remove(action_key)
+    }
+
+    // Removes the calls waiting for a retry and returns their action keys.
+    pub fn abandon_retries(&self) -> Vec<String> {
+        self.retries
+            .take_all()
+            .into_iter()
+            .map(|pending| pending.action_key)
+            .collect()
+    }
+
+    pub async fn is_empty(&self) -> bool {
+        self.queue.lock().await.is_empty()
+    }
+
+    // Flushes the queue whenever the size or the time threshold is hit, and sends again
+    // the calls due for a retry. Never returns.
+    pub async fn run(self: Arc<Self>) {
+        loop {
+            let _ = timeout(self.max_batch_delay, self.flush_notify.notified()).await;
+            self.flush().await;
+            self.retry_due();
+        }
+    }
+
+    // Sends every queued call, max_batch_size calls per multicall.
+    pub async fn flush(self: &Arc<Self>) {
+        let pending_calls: Vec<PendingCall> = std::mem::take(&mut *self.queue.lock().await);
+        for batch in pending_calls.chunks(self.max_batch_size) {
+            let executor = Arc::clone(self);
+            let batch = batch.to_vec();
+            let in_flight = self.in_flight.start();
+            task::spawn(async move { executor.execute_batch(batch, in_flight).await });
+        }
+    }
+
+    // @in_flight: Released once the batch is submitted, its tracking is not waited for.
+    async fn execute_batch(&self, batch: Vec<PendingCall>, in_flight: InFlightGuard) {
+        if batch.len() == 1 {
+            self.execute_one_by_one(batch).await;
+            return;
+        }
+
+        let calls: Vec<Call> = batch.iter().map(|pending| pending.call.clone()).collect();
+        let actions: Vec<(String, &'static str)> = batch
+            .iter()
+            .map(|pending| (pending.action_key.clone(), pending.action_type))
+            .collect();
+
+        let multicall = match self.nonce_manager.execute(calls).await {
+            Ok(multicall) => multicall,
+            Err(e) => {
+                warn!(
+                    "Batch of {} calls could not be sent, executing them one by one: {:?}",
+                    batch.len(),
+                    e
+                );
+                self.execute_one_by_one(batch).await;
+                return;
+            }
+        };
+        drop(in_flight);
+        info!(
+            "Batch of {} calls sent in transaction {:#x}",
+            batch.len(),
+            multicall.transaction_hash
+        );
+
+        let outcome = track_execution(
+            &self.nonce_manager,
+            &self.pool,
+            &actions,
+            multicall.transaction_hash,
+        )
+        .await;
+        if let Some(outcome) = outcome {
+            if outcome.status == ExecutionStatus::Reverted {
+                warn!(
+                    "Batch transaction {:#x} reverted, executing its calls one by one",
+                    multicall.transaction_hash
+                );
+                self.execute_one_by_one(batch).await;
+            }
+        }
+    }
+
+    async fn execute_one_by_one(&self, batch: Vec<PendingCall>) {
+        for pending in batch {
+            self.send_one(pending, 0).await;
+        }
+    }
+
+    // Sends the calls whose retry delay elapsed, one by one.
+    fn retry_due(self: &Arc<Self>) {
+        let due = self.retries.take_due();
+        if due.is_empty() {
+            return;
+        }
+        let executor = Arc::clone(self);
+        let in_flight = self.in_flight.start();
+        task::spawn(async move {
+            let _in_flight = in_flight;
+            for (pending, failed_attempts) in due {
+                executor.send_one(pending, failed_attempts).await;
+            }
+        });
+    }
+
+    // Sends a call on its own, with a max fee raised for each failed attempt when fee
+    // bumping is enabled. A transient failure schedules the call again, other failures
+    // and exhausted retries mark the action as failed.
+    // @failed_attempts: The submissions of this call that already failed.
+    async fn send_one(&self, pending: PendingCall, failed_attempts: u32) {
+        let fee_multiplier = self.retries.fee_multiplier(failed_attempts);
+        let error = match self
+            .nonce_manager
+            .execute_with_fee_multiplier(vec![pending.call.clone()], fee_multiplier)
+            .await
+        {
+            Ok(multicall) => {
+                spawn_tracking(
+                    Arc::clone(&self.nonce_manager),
+                    self.pool.clone(),
+                    pending.action_key,
+                    pending.action_type,
+                    multicall.transaction_hash,
+                )
+                .await;
+                return;
+            }
+            Err(e) => e,
+        };
+
+        let failed_attempts = failed_attempts + 1;
+        let pending = if error.is_retryable() {
+            let action_key = pending.action_key.clone();
+            match self.retries.schedule(pending, failed_attempts) {
+                Ok(delay) => {
+                    SUBMISSION_RETRIES.inc();
+                    warn!(
+                        "Could not send {} (attempt {}), retrying in {:?}: {:?}",
+                        action_key, failed_attempts, delay, error
+                    );
+                    return;
+                }
+                Err(pending) => pending,
+            }
+        } else {
+            pending
+        };
+
+        SUBMISSION_FAILURES.inc();
+        error!(
+            "{} {} execution failed after {} attempts: {:?}",
+            pending.action_type, pending.action_key, failed_attempts, error
+        );
+        if let Err(e) = record_failure(
+            &self.pool,
+            &pending.action_key,
+            pending.action_type,
+            &format!("{:?}", error),
+        )
+        .await
+        {
+            error!("{:?}", e);
+        }
+    }
+}
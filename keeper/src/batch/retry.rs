@@ -0,0 +1,172 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::{config::RetryConfig, nonce::manager::DEFAULT_FEE_MULTIPLIER};
+
+use super::executor::PendingCall;
+
+// The calls whose submission failed with a transient error, sent again after an
+// exponential backoff until they used all their attempts.
+#[derive(Debug)]
+pub struct RetryQueue {
+    config: RetryConfig,
+    entries: Mutex<Vec<RetryEntry>>,
+}
+
+#[derive(Debug)]
+struct RetryEntry {
+    pending_call: PendingCall,
+    failed_attempts: u32,
+    due: Instant,
+}
+
+impl RetryQueue {
+    pub fn new(config: RetryConfig) -> Self {
+        RetryQueue {
+            config,
+            entries: Mutex::new(Vec::new()),
+        }
+    }
+
+    // Schedules a call that failed failed_attempts times.
+    // Returns the delay before it is sent again, or gives the call back once it used all
+    // its attempts.
+    pub fn schedule(
+        &self,
+        pending_call: PendingCall,
+        failed_attempts: u32,
+    ) -> Result<Duration, PendingCall> {
+        if failed_attempts >= self.config.max_attempts {
+            return Err(pending_call);
+        }
+        let delay = self.backoff(failed_attempts);
+        self.entries.lock().unwrap().push(RetryEntry {
+            pending_call,
+            failed_attempts,
+            due: Instant::now() + delay,
+        });
+        Ok(delay)
+    }
+
+    // Removes and returns the calls due to be sent again, with their failed attempts.
+    pub fn take_due(&self) -> Vec<(PendingCall, u32)> {
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap();
+        let (due, waiting): (Vec<RetryEntry>, Vec<RetryEntry>) =
+            entries.drain(..).partition(|entry| entry.due <= now);
+        *entries = waiting;
+        due.into_iter()
+            .map(|entry| (entry.pending_call, entry.failed_attempts))
+            .collect()
+    }
+
+    // Removes and returns every scheduled call, due or not, e.g. on shutdown.
+    pub fn take_all(&self) -> Vec<PendingCall> {
+        let mut entries = self.entries.lock().unwrap();
+        entries.drain(..).map(|entry| entry.pending_call).collect()
+    }
+
+    // Drops the retries of an action, returns the number of calls removed.
+    pub fn remove(&self, action_key: &str) -> usize {
+        let mut entries = self.entries.lock().unwrap();
+        let scheduled = entries.len();
+        entries.retain(|entry| entry.pending_call.action_key != action_key);
+        scheduled - entries.len()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // The delay before the next attempt, doubled after each failure up to the max delay.
+    pub fn backoff(&self, failed_attempts: u32) -> Duration {
+        let exponent = failed_attempts.saturating_sub(1).min(31);
+        self.config
+            .base_delay
+            .saturating_mul(1 << exponent)
+            .min(self.config.max_delay)
+    }
+
+    // The multiplier applied to the fee estimate of a call, raised on each failure when
+    // fee bumping is enabled.
+    pub fn fee_multiplier(&self, failed_attempts: u32) -> f64 {
+        let bump = 1.0 + f64::from(self.config.fee_bump_percent) / 100.0;
+        DEFAULT_FEE_MULTIPLIER * bump.powi(failed_attempts as i32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use starknet::{accounts::Call, core::types::FieldElement};
+
+    use super::*;
+
+    fn retry_queue(fee_bump_percent: u32) -> RetryQueue {
+        RetryQueue::new(RetryConfig {
+            max_attempts: 3,
+            base_delay: Duration::ZERO,
+            max_delay: Duration::from_secs(60),
+            fee_bump_percent,
+        })
+    }
+
+    fn pending_call(action_key: &str) -> PendingCall {
+        PendingCall {
+            action_key: action_key.to_owned(),
+            action_type: "order",
+            call: Call {
+                to: FieldElement::ONE,
+                selector: FieldElement::TWO,
+                calldata: vec![],
+            },
+            execution_fee: Some(10),
+        }
+    }
+
+    #[test]
+    fn test_retry_queue_schedule() {
+        let retries = retry_queue(0);
+
+        assert!(retries.schedule(pending_call("0x1"), 1).is_ok());
+        assert!(retries.schedule(pending_call("0x2"), 2).is_ok());
+        assert!(retries.schedule(pending_call("0x3"), 3).is_err());
+        assert_eq!(retries.remove("0x2"), 1);
+
+        let due = retries.take_due();
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].0.action_key, "0x1");
+        assert_eq!(due[0].1, 1);
+        assert!(retries.is_empty());
+
+        retries.schedule(pending_call("0x4"), 1).unwrap();
+        let abandoned = retries.take_all();
+        assert_eq!(abandoned.len(), 1);
+        assert_eq!(abandoned[0].action_key, "0x4");
+        assert!(retries.is_empty());
+    }
+
+    #[test]
+    fn test_retry_queue_backoff() {
+        let retries = RetryQueue::new(RetryConfig {
+            max_attempts: 10,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(5),
+            fee_bump_percent: 0,
+        });
+
+        assert_eq!(retries.backoff(1), Duration::from_secs(1));
+        assert_eq!(retries.backoff(2), Duration::from_secs(2));
+        assert_eq!(retries.backoff(3), Duration::from_secs(4));
+        assert_eq!(retries.backoff(4), Duration::from_secs(5));
+        assert_eq!(retries.fee_multiplier(3), DEFAULT_FEE_MULTIPLIER);
+
+        let bumped = retry_queue(50);
+        assert!((bumped.fee_multiplier(2) - DEFAULT_FEE_MULTIPLIER * 2.25).abs() < 1e-9);
+    }
+}
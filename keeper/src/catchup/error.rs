@@ -0,0 +1,11 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum CatchUpError {
+    #[error("Database Error")]
+    DatabaseError(String),
+    #[error("Conversion Error")]
+    ConversionError(String),
+    #[error("Smart Contract Error")]
+    SmartContractError(String),
+}
@@ -0,0 +1,122 @@
+use std::sync::Arc;
+
+use log::debug;
+use sqlx::PgPool;
+use starknet::{
+    accounts::SingleOwnerAccount,
+    core::types::FieldElement,
+    providers::jsonrpc::{HttpTransport, JsonRpcClient},
+    signers::LocalWallet,
+};
+
+use crate::{
+    config::ContractAddresses,
+    types::{DataStore, SatoruAction},
+};
+
+use super::error::CatchUpError;
+
+// The actions whose latest keeper transaction is still pending or went through, and the
+// ones given up after their submission kept failing. Reverted and dropped ones are tried again.
+const EXECUTED_STATUSES: &str = "('pending', 'accepted', 'failed')";
+
+// Finds the orders, deposits and withdrawals that were indexed but never executed,
+// e.g. because the keeper was down when they were created, and that still wait for
+// execution on chain.
+// Returns each action along with the table it comes from.
+// @pool: A reference to a connection pool for PostgreSQL.
+// @account: The keeper account, used to read the DataStore.
+// @contracts: The Satoru contract addresses.
+pub async fn get_pending_actions(
+    pool: &PgPool,
+    account: Arc<SingleOwnerAccount<JsonRpcClient<HttpTransport>, LocalWallet>>,
+    contracts: &ContractAddresses,
+) -> Result<Vec<(&'static str, SatoruAction)>, CatchUpError> {
+    let data_store = DataStore::new(contracts.data_store, account);
+
+    let orders: Vec<SatoruAction> = sqlx::query_as(&format!(
+        "SELECT * FROM orders o
+         WHERE (o.is_frozen IS NULL OR o.is_frozen = FALSE)
+         AND NOT EXISTS (SELECT 1 FROM order_executed e WHERE e.key = o.key)
+         AND NOT EXISTS (SELECT 1 FROM keeper_executions k
+                         WHERE k.action_key = o.key AND k.status IN {})",
+        EXECUTED_STATUSES
+    ))
+    .fetch_all(pool)
+    .await
+    .map_err(|e| CatchUpError::DatabaseError(format!("orders: {}", e)))?;
+    let deposits = get_unexecuted(pool, "deposits").await?;
+    let withdrawals = get_unexecuted(pool, "withdrawals").await?;
+
+    let candidates = orders
+        .into_iter()
+        .map(|order| ("order", order))
+        .chain(deposits.into_iter().map(|deposit| ("deposit", deposit)))
+        .chain(
+            withdrawals
+                .into_iter()
+                .map(|withdrawal| ("withdrawal", withdrawal)),
+        );
+
+    let mut pending_actions = Vec::new();
+    for (action_type, action) in candidates {
+        if is_pending_on_chain(&data_store, action_type, &action.key).await? {
+            pending_actions.push((action_type, action));
+        } else {
+            debug!(
+                "{} {} is no longer stored on chain, skipping it",
+                action_type, action.key
+            );
+        }
+    }
+    Ok(pending_actions)
+}
+
+// The rows of a deposits or withdrawals table without a keeper execution.
+async fn get_unexecuted(pool: &PgPool, table: &str) -> Result<Vec<SatoruAction>, CatchUpError> {
+    sqlx::query_as(&format!(
+        "SELECT * FROM {table} a
+         WHERE NOT EXISTS (SELECT 1 FROM keeper_executions k
+                           WHERE k.action_key = a.key AND k.status IN {statuses})",
+        table = table,
+        statuses = EXECUTED_STATUSES
+    ))
+    .fetch_all(pool)
+    .await
+    .map_err(|e| CatchUpError::DatabaseError(format!("{}: {}", table, e)))
+}
+
+// Whether an action is still stored in the DataStore. Executed and cancelled actions
+// are removed from it, reading them back returns an empty struct.
+// @data_store: The DataStore contract.
+// @action_type: The kind of action, order, deposit or withdrawal.
+// @key: The key of the action.
+pub async fn is_pending_on_chain(
+    data_store: &DataStore<Arc<SingleOwnerAccount<JsonRpcClient<HttpTransport>, LocalWallet>>>,
+    action_type: &str,
+    key: &str,
+) -> Result<bool, CatchUpError> {
+    let key = FieldElement::from_hex_be(key)
+        .map_err(|e| CatchUpError::ConversionError(format!("key {}: {}", key, e)))?;
+    let stored_key = match action_type {
+        "order" => data_store
+            .get_order(&key)
+            .call()
+            .await
+            .map(|order| order.key),
+        "deposit" => data_store
+            .get_deposit(&key)
+            .call()
+            .await
+            .map(|deposit| deposit.key),
+        _ => data_store
+            .get_withdrawal(&key)
+            .call()
+            .await
+            .map(|withdrawal| withdrawal.key),
+    }
+    .map_err(|e| {
+        CatchUpError::SmartContractError(format!("{} {:#x}: {:?}", action_type, key, e))
+    })?;
+    Ok(stored_key == key)
+}
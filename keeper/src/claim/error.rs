@@ -0,0 +1,7 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ClaimError {
+    #[error("Database Error")]
+    DatabaseError(String),
+}
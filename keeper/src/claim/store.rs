@@ -0,0 +1,66 @@
+use std::time::Duration;
+
+use sqlx::PgPool;
+
+use crate::tracker::receipt::ExecutionStatus;
+
+use super::error::ClaimError;
+
+// Claims an action so that only one keeper replica executes it.
+// The claim is granted when the action is unclaimed, already claimed by this keeper,
+// or when another keeper's lease expired without its transaction being accepted.
+// Returns whether this keeper holds the claim.
+// @pool: A reference to a connection pool for PostgreSQL.
+// @action_key: The key of the order, deposit, withdrawal or position.
+// @keeper_id: The identifier of this keeper replica.
+// @lease: How long the claim is held before other keepers may take it over.
+pub async fn claim_action(
+    pool: &PgPool,
+    action_key: &str,
+    keeper_id: &str,
+    lease: Duration,
+) -> Result<bool, ClaimError> {
+    let claimed: Option<String> = sqlx::query_scalar(
+        "INSERT INTO keeper_claims (action_key, keeper_id, expires_at)
+         VALUES ($1, $2, NOW() + $3 * INTERVAL '1 millisecond')
+         ON CONFLICT (action_key) DO UPDATE SET
+            keeper_id = EXCLUDED.keeper_id,
+            claimed_at = NOW(),
+            expires_at = EXCLUDED.expires_at
+         WHERE keeper_claims.keeper_id = EXCLUDED.keeper_id
+            OR (keeper_claims.expires_at < NOW()
+                AND NOT EXISTS (SELECT 1 FROM keeper_executions k
+                                WHERE k.action_key = keeper_claims.action_key
+                                AND k.status = $4))
+         RETURNING action_key",
+    )
+    .bind(action_key)
+    .bind(keeper_id)
+    .bind(lease.as_millis() as i64)
+    .bind(ExecutionStatus::Accepted.as_str())
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| ClaimError::DatabaseError(format!("Could not claim {}: {}", action_key, e)))?;
+    Ok(claimed.is_some())
+}
+
+// Gives up a claim held by this keeper, e.g. when its call could not be built,
+// so that another keeper can execute the action right away.
+// @pool: A reference to a connection pool for PostgreSQL.
+// @action_key: The key of the claimed action.
+// @keeper_id: The identifier of this keeper replica.
+pub async fn release_claim(
+    pool: &PgPool,
+    action_key: &str,
+    keeper_id: &str,
+) -> Result<(), ClaimError> {
+    sqlx::query("DELETE FROM keeper_claims WHERE action_key = $1 AND keeper_id = $2")
+        .bind(action_key)
+        .bind(keeper_id)
+        .execute(pool)
+        .await
+        .map_err(|e| {
+            ClaimError::DatabaseError(format!("Could not release {}: {}", action_key, e))
+        })?;
+    Ok(())
+}
@@ -12,4 +12,18 @@ pub enum KeeperError {
     PublicKeyNotSet(),
     #[error("Wrong launch params")]
     WrongParam(),
+    #[error("Invalid keeper configuration")]
+    ConfigError(String),
+    #[error("Invalid keeper configuration: {}", .0.join("; "))]
+    InvalidConfig(Vec<String>),
+    #[error("Database Error")]
+    DatabaseError(String),
+    #[error("Execution Error")]
+    ExecutionError(String),
+    #[error("Action claimed by another keeper")]
+    ActionClaimed(String),
+    #[error("Simulation reverted")]
+    SimulationReverted(String),
+    #[error("Execution fee too low")]
+    Unprofitable(String),
 }
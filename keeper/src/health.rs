@@ -0,0 +1,95 @@
+use std::{net::SocketAddr, sync::Arc};
+
+use actix_web::{dev::Server, get, web, App, HttpResponse, HttpServer, Responder};
+use serde::Serialize;
+
+use crate::{listen_db::ListenerHealth, metrics};
+
+#[derive(Debug, Serialize)]
+struct HealthReport {
+    status: &'static str,
+    listener: &'static str,
+    listener_reconnects: u64,
+    last_notification_at: Option<u64>,
+}
+
+// Reports the keeper as unhealthy while its started listener is disconnected.
+#[get("/health")]
+async fn health(listener_health: web::Data<Arc<ListenerHealth>>) -> impl Responder {
+    let listener = if !listener_health.is_started() {
+        "not started"
+    } else if listener_health.is_connected() {
+        "connected"
+    } else {
+        "disconnected"
+    };
+    let healthy = listener != "disconnected";
+    let report = HealthReport {
+        status: if healthy { "ok" } else { "unhealthy" },
+        listener,
+        listener_reconnects: listener_health.reconnects(),
+        last_notification_at: listener_health.last_notification_at(),
+    };
+    if healthy {
+        HttpResponse::Ok().json(report)
+    } else {
+        HttpResponse::ServiceUnavailable().json(report)
+    }
+}
+
+#[get("/metrics")]
+async fn metrics_endpoint() -> impl Responder {
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(metrics::render())
+}
+
+// Serves /health and the Prometheus /metrics on addr, the returned server must be awaited.
+// @addr: The address to listen on.
+// @listener_health: The connection state of the keeper listener.
+pub fn serve_health(
+    addr: SocketAddr,
+    listener_health: Arc<ListenerHealth>,
+) -> std::io::Result<Server> {
+    let listener_health = web::Data::new(listener_health);
+    let server = HttpServer::new(move || {
+        App::new()
+            .app_data(listener_health.clone())
+            .service(health)
+            .service(metrics_endpoint)
+    })
+    .workers(1)
+    .disable_signals()
+    .bind(addr)?
+    .run();
+    Ok(server)
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{http::StatusCode, test};
+
+    use super::*;
+
+    #[actix_rt::test]
+    async fn test_health() {
+        let listener_health = Arc::new(ListenerHealth::default());
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(listener_health))
+                .service(health)
+                .service(metrics_endpoint),
+        )
+        .await;
+
+        let response =
+            test::call_service(&app, test::TestRequest::get().uri("/health").to_request()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response =
+            test::call_service(&app, test::TestRequest::get().uri("/metrics").to_request()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = test::read_body(response).await;
+        assert!(String::from_utf8_lossy(&body).contains("keeper_price_cache_hits_total"));
+    }
+}
@@ -0,0 +1,1026 @@
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use log::{debug, error, info, warn};
+use sqlx::PgPool;
+use starknet::{
+    accounts::{Account, Call, ConnectedAccount, ExecutionEncoding, SingleOwnerAccount},
+    core::types::{
+        BlockId, BlockTag, ExecuteInvocation, FieldElement, SimulatedTransaction, TransactionTrace,
+    },
+    providers::{jsonrpc::HttpTransport, JsonRpcClient, Provider},
+    signers::{LocalWallet, SigningKey},
+};
+use tokio::{sync::watch, task, time::sleep};
+
+use crate::{
+    batch::executor::{BatchExecutor, PendingCall},
+    catchup::process::get_pending_actions,
+    claim::store::{claim_action, release_claim},
+    config::{chain_id_name, ContractAddresses, KeeperConfig},
+    error::KeeperError,
+    liquidation::{
+        execution::{execute_liquidations, get_execute_liquidation_call},
+        process::get_liquidatable_positions,
+        schedule::{ScanSchedule, ScanTrigger},
+        utils::is_liquidatable_call,
+    },
+    listen_db::{start_listening, ListenerHealth},
+    metrics::{
+        LIQUIDATION_SCANS, LIQUIDATION_SCAN_DURATION_MS, PREFLIGHT_REVERTS, PRICE_CACHE_HITS,
+        PRICE_CACHE_MISSES, UNPROFITABLE_DEFERRED, UNPROFITABLE_SKIPPED, UNPROFITABLE_SUBMITTED,
+    },
+    nonce::manager::NonceManager,
+    preflight::{
+        policy::{decode_revert_reason, PreflightDecision},
+        profitability::is_profitable,
+        retries::PreflightRetries,
+        store::record_preflight_revert,
+    },
+    price::{
+        provider::{price_provider_from_config, PriceProvider},
+        utils::get_float_market_prices,
+    },
+    query::{get_deposit, get_market, get_order, get_position, get_withdrawal},
+    supervisor::in_flight::InFlight,
+    token::registry::TokenRegistry,
+    tracker::{process::spawn_tracking, store::has_live_execution},
+    trade::{
+        deposit::handle::{get_execute_deposit_call, handle_deposit},
+        order::handle::{get_execute_order_call, handle_order},
+        utils::get_latest_block_info,
+        withdrawal::handle::{get_execute_withdrawal_call, handle_withdrawal},
+    },
+    trigger::{execution::execute_trigger_positions, process::get_triggerable_orders},
+    types::{ActionType, Payload, Position, SatoruAction},
+};
+
+// How often the execution mode looks for actions to simulate again.
+const PREFLIGHT_RETRY_INTERVAL: Duration = Duration::from_secs(1);
+
+// The keeper loops that can be run.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KeeperMode {
+    Execution,
+    Liquidation,
+    Trigger,
+}
+
+impl FromStr for KeeperMode {
+    type Err = KeeperError;
+
+    fn from_str(mode: &str) -> Result<Self, Self::Err> {
+        match mode {
+            "execution" => Ok(KeeperMode::Execution),
+            "liquidation" => Ok(KeeperMode::Liquidation),
+            "trigger" => Ok(KeeperMode::Trigger),
+            _ => Err(KeeperError::WrongParam()),
+        }
+    }
+}
+
+// A keeper connected to a Starknet node and to the indexer database.
+// It owns everything needed to execute Satoru actions: the account, the database pool,
+// the contract addresses, the token registry and the price provider.
+pub struct Keeper {
+    config: KeeperConfig,
+    pool: PgPool,
+    nonce_manager: Arc<NonceManager>,
+    batch_executor: Arc<BatchExecutor>,
+    token_registry: Arc<TokenRegistry>,
+    price_provider: Arc<dyn PriceProvider>,
+    // Actions being handled or submitted, waited for on shutdown.
+    in_flight: Arc<InFlight>,
+    // Set once a shutdown is requested, the loops stop at their next iteration.
+    shutdown: watch::Sender<bool>,
+    // Connection state of the execution mode listener.
+    listener_health: Arc<ListenerHealth>,
+    // Actions deferred by the pre-flight checks, to be handled again.
+    preflight_retries: PreflightRetries,
+}
+
+impl Keeper {
+    // Connects the keeper, prices are fetched from the providers set in the config.
+    pub async fn new(config: KeeperConfig) -> Result<Self, KeeperError> {
+        Self::connect(config, None).await
+    }
+
+    // Connects the keeper with its own price provider.
+    pub async fn with_price_provider(
+        config: KeeperConfig,
+        price_provider: Arc<dyn PriceProvider>,
+    ) -> Result<Self, KeeperError> {
+        Self::connect(config, Some(price_provider)).await
+    }
+
+    // Loads the token registry, the only time it is read, and builds the price providers
+    // from the config unless one is given.
+    async fn connect(
+        config: KeeperConfig,
+        price_provider: Option<Arc<dyn PriceProvider>>,
+    ) -> Result<Self, KeeperError> {
+        let account = Arc::new(build_account(&config));
+        let token_registry = TokenRegistry::load(&config.token_registry)
+            .map(Arc::new)
+            .map_err(|e| KeeperError::ConfigError(format!("token registry: {:?}", e)))?;
+        let price_provider = match price_provider {
+            Some(price_provider) => price_provider,
+            None => price_provider_from_config(
+                account.clone(),
+                config.contracts.oracle,
+                &config.price,
+                Arc::clone(&token_registry),
+            )
+            .map_err(|e| KeeperError::ConfigError(format!("price provider: {:?}", e)))?,
+        };
+
+        check_chain_id(&config, &account).await?;
+
+        let pool = PgPool::connect(&config.database_url)
+            .await
+            .map_err(|e| KeeperError::DatabaseError(format!("Could not connect: {}", e)))?;
+
+        match token_registry.get_unregistered_market_tokens(&pool).await {
+            Ok(tokens) if !tokens.is_empty() => {
+                warn!(
+                    "Markets use tokens missing from the token registry, they cannot be priced: {:?}",
+                    tokens
+                );
+            }
+            Ok(_) => {}
+            Err(e) => error!(
+                "Could not check market tokens against the registry: {:?}",
+                e
+            ),
+        }
+        info!("Pricing tokens with {}", price_provider.name());
+
+        let nonce_manager = Arc::new(NonceManager::new(account));
+        let in_flight = Arc::new(InFlight::default());
+        let batch_executor = Arc::new(BatchExecutor::new(
+            Arc::clone(&nonce_manager),
+            pool.clone(),
+            config.batch_max_size,
+            config.batch_max_delay,
+            Arc::clone(&in_flight),
+            config.retry.clone(),
+        ));
+        task::spawn(Arc::clone(&batch_executor).run());
+        let preflight_retries =
+            PreflightRetries::new(config.preflight.max_retries, config.preflight.retry_delay);
+
+        Ok(Keeper {
+            config,
+            pool,
+            nonce_manager,
+            batch_executor,
+            token_registry,
+            price_provider,
+            in_flight,
+            shutdown: watch::channel(false).0,
+            listener_health: Arc::new(ListenerHealth::default()),
+            preflight_retries,
+        })
+    }
+
+    pub fn config(&self) -> &KeeperConfig {
+        &self.config
+    }
+
+    pub fn account(&self) -> Arc<SingleOwnerAccount<JsonRpcClient<HttpTransport>, LocalWallet>> {
+        self.nonce_manager.account()
+    }
+
+    pub fn pool(&self) -> &PgPool {
+        &self.pool
+    }
+
+    pub fn contracts(&self) -> &ContractAddresses {
+        &self.config.contracts
+    }
+
+    pub fn token_registry(&self) -> &Arc<TokenRegistry> {
+        &self.token_registry
+    }
+
+    pub fn price_provider(&self) -> &Arc<dyn PriceProvider> {
+        &self.price_provider
+    }
+
+    pub fn nonce_manager(&self) -> &Arc<NonceManager> {
+        &self.nonce_manager
+    }
+
+    pub fn batch_executor(&self) -> &Arc<BatchExecutor> {
+        &self.batch_executor
+    }
+
+    pub fn listener_health(&self) -> &Arc<ListenerHealth> {
+        &self.listener_health
+    }
+
+    // Asks the keeper loops to stop, they finish the action at hand first.
+    pub fn request_shutdown(&self) {
+        self.shutdown.send_replace(true);
+    }
+
+    pub fn is_shutting_down(&self) -> bool {
+        *self.shutdown.borrow()
+    }
+
+    // Returns once a shutdown is requested.
+    pub async fn shutdown_requested(&self) {
+        let mut shutdown = self.shutdown.subscribe();
+        let _ = shutdown.wait_for(|requested| *requested).await;
+    }
+
+    // Waits for the actions being handled and every queued call to be submitted.
+    // Transactions already sent are not waited for.
+    // The actions still waiting for a retry are given up and their claims released,
+    // they have no execution recorded so the next catch-up handles them again.
+    pub async fn drain(&self) {
+        loop {
+            self.in_flight.wait_idle().await;
+            if self.batch_executor.is_empty().await {
+                break;
+            }
+            self.batch_executor.flush().await;
+        }
+
+        let mut abandoned = self.batch_executor.abandon_retries();
+        abandoned.extend(self.preflight_retries.take_all());
+        abandoned.sort();
+        abandoned.dedup();
+        if abandoned.is_empty() {
+            return;
+        }
+        warn!(
+            "Abandoning {} actions waiting for a retry: {}",
+            abandoned.len(),
+            abandoned.join(", ")
+        );
+        for action_key in abandoned {
+            self.release(&action_key).await;
+        }
+    }
+
+    // Claims an action for this keeper, so that only one replica executes it.
+    // Dry runs do not claim anything. A claim that cannot be checked is refused.
+    // Returns whether this keeper may go on with the action.
+    pub async fn claim(&self, action_type: &str, action_key: &str) -> bool {
+        if self.config.dry_run {
+            return true;
+        }
+        match claim_action(
+            &self.pool,
+            action_key,
+            &self.config.keeper_id,
+            self.config.claim_lease,
+        )
+        .await
+        {
+            Ok(true) => true,
+            Ok(false) => {
+                debug!(
+                    "{} {} is claimed by another keeper, skipping it",
+                    action_type, action_key
+                );
+                false
+            }
+            Err(e) => {
+                error!("Could not claim {} {}: {:?}", action_type, action_key, e);
+                false
+            }
+        }
+    }
+
+    // Gives up the claim on an action this keeper will not execute after all.
+    pub async fn release(&self, action_key: &str) {
+        if self.config.dry_run {
+            return;
+        }
+        if let Err(e) = release_claim(&self.pool, action_key, &self.config.keeper_id).await {
+            error!("Could not release claim on {}: {:?}", action_key, e);
+        }
+    }
+
+    // Whether calls are only simulated instead of sent.
+    pub fn dry_run(&self) -> bool {
+        self.config.dry_run
+    }
+
+    // Builds the execute call of an action.
+    // @action_type: The table the action comes from, order, deposit or withdrawal.
+    // @action: The action to execute.
+    pub async fn get_execute_call(
+        &self,
+        action_type: &str,
+        action: SatoruAction,
+    ) -> Result<Call, KeeperError> {
+        let account = self.account();
+        let contracts = self.contracts();
+        let price_provider = self.price_provider.as_ref();
+        match action_type {
+            "order" => get_execute_order_call(action, account, contracts, price_provider)
+                .await
+                .map_err(|e| KeeperError::ExecutionError(format!("{:?}", e))),
+            "deposit" => get_execute_deposit_call(action, account, contracts, price_provider)
+                .await
+                .map_err(|e| KeeperError::ExecutionError(format!("{:?}", e))),
+            "withdrawal" => get_execute_withdrawal_call(action, account, contracts, price_provider)
+                .await
+                .map_err(|e| KeeperError::ExecutionError(format!("{:?}", e))),
+            other => Err(KeeperError::ExecutionError(format!(
+                "Unknown action type: {}",
+                other
+            ))),
+        }
+    }
+
+    // Executes an order right away and returns the transaction hash.
+    pub async fn execute_order(&self, order: SatoruAction) -> Result<FieldElement, KeeperError> {
+        self.execute_action("order", order).await
+    }
+
+    // Executes a deposit right away and returns the transaction hash.
+    pub async fn execute_deposit(
+        &self,
+        deposit: SatoruAction,
+    ) -> Result<FieldElement, KeeperError> {
+        self.execute_action("deposit", deposit).await
+    }
+
+    // Executes a withdrawal right away and returns the transaction hash.
+    pub async fn execute_withdrawal(
+        &self,
+        withdrawal: SatoruAction,
+    ) -> Result<FieldElement, KeeperError> {
+        self.execute_action("withdrawal", withdrawal).await
+    }
+
+    async fn execute_action(
+        &self,
+        action_type: &'static str,
+        action: SatoruAction,
+    ) -> Result<FieldElement, KeeperError> {
+        let action_key = action.key.clone();
+        let execution_fee = action.execution_fee;
+        if !self.claim(action_type, &action_key).await {
+            return Err(KeeperError::ActionClaimed(format!(
+                "{} {}",
+                action_type, action_key
+            )));
+        }
+        let result = match self.get_execute_call(action_type, action).await {
+            Ok(call) => {
+                self.submit(PendingCall {
+                    action_key: action_key.clone(),
+                    action_type,
+                    call,
+                    execution_fee: Some(execution_fee),
+                })
+                .await
+            }
+            Err(e) => Err(e),
+        };
+        if result.is_err() {
+            self.release(&action_key).await;
+        }
+        result
+    }
+
+    // Liquidates a position and returns the transaction hash.
+    pub async fn liquidate(&self, position: Position) -> Result<FieldElement, KeeperError> {
+        let position_key = format!("{:#x}", position.key);
+        if !self.claim("liquidation", &position_key).await {
+            return Err(KeeperError::ActionClaimed(format!(
+                "position {}",
+                position_key
+            )));
+        }
+        let call = get_execute_liquidation_call(
+            position,
+            self.account(),
+            self.contracts(),
+            self.price_provider.as_ref(),
+        )
+        .await
+        .map_err(|e| KeeperError::ExecutionError(format!("{:?}", e)));
+        let result = match call {
+            Ok(call) => {
+                self.submit(PendingCall {
+                    action_key: position_key.clone(),
+                    action_type: "liquidation",
+                    call,
+                    execution_fee: None,
+                })
+                .await
+            }
+            Err(e) => Err(e),
+        };
+        if result.is_err() {
+            self.release(&position_key).await;
+        }
+        result
+    }
+
+    // Queues an execute call for the next batch, in a dry run the call is only simulated.
+    // A call whose pre-flight simulation reverts, or costing more than its execution fee,
+    // is skipped or handled again later, as the pre-flight and profitability policies decide.
+    pub async fn enqueue(&self, pending_call: PendingCall) {
+        if self.config.dry_run {
+            self.simulate_dry_run(
+                &pending_call.action_key,
+                pending_call.action_type,
+                pending_call.call,
+            )
+            .await;
+            return;
+        }
+
+        let action_key = pending_call.action_key.clone();
+        let action_type = pending_call.action_type;
+        let decision = match self
+            .preflight(&action_key, action_type, &pending_call.call)
+            .await
+        {
+            Err((decision, _)) => Some(decision),
+            Ok(simulated_fee) => self.check_profitability(&pending_call, simulated_fee).await,
+        };
+        match decision {
+            None => {
+                self.preflight_retries.forget(&action_key);
+                self.batch_executor.push(pending_call).await;
+            }
+            Some(PreflightDecision::Retry) if self.preflight_retries.schedule(&action_key) => {
+                info!("Handling {} {} again later", action_type, action_key);
+            }
+            Some(_) => {
+                info!("Skipping {} {}", action_type, action_key);
+                self.preflight_retries.forget(&action_key);
+                self.release(&action_key).await;
+            }
+        }
+    }
+
+    // Sends a call on its own and tracks its transaction in the background.
+    // In a dry run the call is only simulated and the returned hash is zero.
+    // Calls that fail the pre-flight or profitability checks are not sent.
+    async fn submit(&self, pending_call: PendingCall) -> Result<FieldElement, KeeperError> {
+        let action_key = pending_call.action_key.clone();
+        let action_type = pending_call.action_type;
+        if self.config.dry_run {
+            self.simulate_dry_run(&action_key, action_type, pending_call.call)
+                .await;
+            return Ok(FieldElement::ZERO);
+        }
+        let simulated_fee = self
+            .preflight(&action_key, action_type, &pending_call.call)
+            .await
+            .map_err(|(decision, reason)| {
+                KeeperError::SimulationReverted(format!(
+                    "{} {} not sent ({}): {}",
+                    action_type, action_key, decision, reason
+                ))
+            })?;
+        if let Some(decision) = self.check_profitability(&pending_call, simulated_fee).await {
+            return Err(KeeperError::Unprofitable(format!(
+                "{} {} not sent ({})",
+                action_type, action_key, decision
+            )));
+        }
+
+        let _in_flight = self.in_flight.start();
+        let multicall = self
+            .nonce_manager
+            .execute(vec![pending_call.call])
+            .await
+            .map_err(|e| KeeperError::ExecutionError(format!("{:?}", e)))?;
+        spawn_tracking(
+            Arc::clone(&self.nonce_manager),
+            self.pool.clone(),
+            action_key,
+            action_type,
+            multicall.transaction_hash,
+        )
+        .await;
+        Ok(multicall.transaction_hash)
+    }
+
+    async fn simulate_dry_run(&self, action_key: &str, action_type: &str, call: Call) {
+        match self.simulate(vec![call]).await {
+            Ok(simulation) => match revert_reason(&simulation) {
+                Some(reason) => warn!(
+                    "Dry run: {} {} would revert: {}",
+                    action_type,
+                    action_key,
+                    decode_revert_reason(&reason)
+                ),
+                None => info!(
+                    "Dry run: {} {} would succeed, estimated fee {}",
+                    action_type, action_key, simulation.fee_estimation.overall_fee
+                ),
+            },
+            Err(e) => error!(
+                "Dry run: {} {} could not be simulated: {:?}",
+                action_type, action_key, e
+            ),
+        }
+    }
+
+    // Simulates an execute call before it is sent, when pre-flight simulation is enabled.
+    // A reverted simulation is recorded with its decoded revert reason.
+    // Returns the fee estimated by the simulation when the call may be sent, None when it
+    // was not simulated, or else the decision of the policy along with the reason.
+    // A call that could not be simulated at all is sent anyway.
+    async fn preflight(
+        &self,
+        action_key: &str,
+        action_type: &str,
+        call: &Call,
+    ) -> Result<Option<u128>, (PreflightDecision, String)> {
+        if !self.config.preflight.enabled {
+            return Ok(None);
+        }
+        let simulation = match self.simulate(vec![call.clone()]).await {
+            Ok(simulation) => simulation,
+            Err(e) => {
+                warn!(
+                    "Could not simulate {} {}, sending it anyway: {:?}",
+                    action_type, action_key, e
+                );
+                return Ok(None);
+            }
+        };
+        let simulated_fee =
+            u128::try_from(simulation.fee_estimation.overall_fee).unwrap_or(u128::MAX);
+        let Some(reason) = revert_reason(&simulation) else {
+            return Ok(Some(simulated_fee));
+        };
+        let reason = decode_revert_reason(&reason);
+        let decision = self.config.preflight.policy.decide(&reason);
+        PREFLIGHT_REVERTS.inc();
+        warn!(
+            "{} {} would revert: {}, decision: {}",
+            action_type, action_key, reason, decision
+        );
+        if let Err(e) =
+            record_preflight_revert(&self.pool, action_key, action_type, &reason, decision).await
+        {
+            error!("{:?}", e);
+        }
+        match decision {
+            PreflightDecision::Submit => Ok(Some(simulated_fee)),
+            decision => Err((decision, reason)),
+        }
+    }
+
+    // Estimates the fee of a call and compares it with the execution fee paid for it,
+    // when the profitability check is enabled. Liquidations are not checked.
+    // Returns the decision of the policy for a call costing too much, None when the call
+    // may be sent. A call whose fee could not be estimated is sent anyway.
+    // @simulated_fee: The fee estimated by the pre-flight simulation, the fee is only
+    // estimated again when the call was not simulated.
+    async fn check_profitability(
+        &self,
+        pending_call: &PendingCall,
+        simulated_fee: Option<u128>,
+    ) -> Option<PreflightDecision> {
+        let profitability = &self.config.profitability;
+        if !profitability.enabled {
+            return None;
+        }
+        let execution_fee = pending_call.execution_fee?;
+        let estimated_fee = match simulated_fee {
+            Some(simulated_fee) => simulated_fee,
+            None => match self
+                .account()
+                .execute(vec![pending_call.call.clone()])
+                .estimate_fee()
+                .await
+            {
+                Ok(estimate) => u128::try_from(estimate.overall_fee).unwrap_or(u128::MAX),
+                Err(e) => {
+                    warn!(
+                        "Could not estimate the fee of {} {}, sending it anyway: {}",
+                        pending_call.action_type, pending_call.action_key, e
+                    );
+                    return None;
+                }
+            },
+        };
+        if is_profitable(estimated_fee, execution_fee, profitability.margin_bps) {
+            debug!(
+                "{} {} costs about {} for an execution fee of {}",
+                pending_call.action_type, pending_call.action_key, estimated_fee, execution_fee
+            );
+            return None;
+        }
+
+        match profitability.decision {
+            PreflightDecision::Skip => UNPROFITABLE_SKIPPED.inc(),
+            PreflightDecision::Retry => UNPROFITABLE_DEFERRED.inc(),
+            PreflightDecision::Submit => UNPROFITABLE_SUBMITTED.inc(),
+        }
+        warn!(
+            "{} {} costs about {} but its execution fee is {}, decision: {}",
+            pending_call.action_type,
+            pending_call.action_key,
+            estimated_fee,
+            execution_fee,
+            profitability.decision
+        );
+        match profitability.decision {
+            PreflightDecision::Submit => None,
+            decision => Some(decision),
+        }
+    }
+
+    // Handles again the actions deferred by the pre-flight checks, never returns.
+    // An action that no longer exists is forgotten.
+    async fn retry_preflight(&self) {
+        loop {
+            sleep(PREFLIGHT_RETRY_INTERVAL).await;
+            for action_key in self.preflight_retries.take_due() {
+                if self.is_shutting_down() {
+                    return;
+                }
+                let _in_flight = self.in_flight.start();
+                match self.find_action(&action_key).await {
+                    Ok((action_type, action)) => self.handle_action(action_type, action).await,
+                    Err(e) => {
+                        debug!("Not simulating {} again: {:?}", action_key, e);
+                        self.preflight_retries.forget(&action_key);
+                        self.release(&action_key).await;
+                    }
+                }
+            }
+        }
+    }
+
+    // Simulates the calls as one multicall from the keeper account, nothing is sent.
+    pub async fn simulate(&self, calls: Vec<Call>) -> Result<SimulatedTransaction, KeeperError> {
+        self.account()
+            .execute(calls)
+            .simulate(false, false)
+            .await
+            .map_err(|e| KeeperError::ExecutionError(format!("Simulation failed: {}", e)))
+    }
+
+    // Finds a pending order, deposit or withdrawal by key.
+    // Returns the table the action comes from along with the action.
+    pub async fn find_action(
+        &self,
+        key: &str,
+    ) -> Result<(&'static str, SatoruAction), KeeperError> {
+        let not_found = |result: Result<SatoruAction, sqlx::Error>| match result {
+            Ok(action) => Ok(Some(action)),
+            Err(sqlx::Error::RowNotFound) => Ok(None),
+            Err(e) => Err(KeeperError::DatabaseError(e.to_string())),
+        };
+        if let Some(order) = not_found(get_order(key.to_owned(), &self.pool).await)? {
+            return Ok(("order", order));
+        }
+        if let Some(deposit) = not_found(get_deposit(key.to_owned(), &self.pool).await)? {
+            return Ok(("deposit", deposit));
+        }
+        if let Some(withdrawal) = not_found(get_withdrawal(key.to_owned(), &self.pool).await)? {
+            return Ok(("withdrawal", withdrawal));
+        }
+        Err(KeeperError::DatabaseError(format!(
+            "No order, deposit or withdrawal with key {}",
+            key
+        )))
+    }
+
+    // Asks the Reader whether a position can be liquidated at the current prices.
+    // Returns whether it is liquidatable and the reason given by the Reader.
+    pub async fn check_position(&self, key: &str) -> Result<(bool, FieldElement), KeeperError> {
+        let position = get_position(key.to_owned(), &self.pool)
+            .await
+            .map_err(|e| KeeperError::DatabaseError(format!("position {}: {}", key, e)))?;
+        let market = get_market(position.market.0.to_string(), &self.pool)
+            .await
+            .map_err(|e| KeeperError::DatabaseError(format!("market of {}: {}", key, e)))?;
+        let (_block_number, timestamp) = get_latest_block_info(self.account())
+            .await
+            .map_err(|e| KeeperError::ExecutionError(format!("{:?}", e)))?;
+        let prices =
+            get_float_market_prices(self.price_provider.as_ref(), &market, timestamp.to_string())
+                .await
+                .map_err(|e| KeeperError::ExecutionError(format!("{:?}", e)))?;
+
+        is_liquidatable_call(
+            self.account(),
+            self.contracts(),
+            position,
+            market,
+            prices,
+            true,
+        )
+        .await
+        .map_err(|e| KeeperError::ExecutionError(format!("{:?}", e)))
+    }
+
+    // Handles the pending orders, deposits and withdrawals that were never executed,
+    // through the same handlers as the notified ones.
+    pub async fn catch_up(&self) {
+        let pending_actions =
+            match get_pending_actions(&self.pool, self.account(), self.contracts()).await {
+                Ok(pending_actions) => pending_actions,
+                Err(e) => {
+                    error!("Could not look for missed actions: {:?}", e);
+                    return;
+                }
+            };
+        if pending_actions.is_empty() {
+            debug!("No missed action to catch up on");
+            return;
+        }
+
+        info!("Catching up on {} missed actions", pending_actions.len());
+        for (action_type, action) in pending_actions {
+            if self.is_shutting_down() {
+                return;
+            }
+            let _in_flight = self.in_flight.start();
+            self.handle_action(action_type, action).await;
+        }
+    }
+
+    // Builds the execute call of an action and queues it, errors are logged.
+    // @action_type: The table the action comes from, order, deposit or withdrawal.
+    pub async fn handle_action(&self, action_type: &str, action: SatoruAction) {
+        match action_type {
+            "order" => handle_order(self, action).await,
+            "deposit" => handle_deposit(self, action).await,
+            "withdrawal" => handle_withdrawal(self, action).await,
+            other => error!("Unknown action type: {}", other),
+        }
+    }
+
+    // Re-evaluates an updated action. Its queued call, built from the previous values,
+    // is dropped, and a frozen order is not executed. Otherwise the call is built again,
+    // unless the action was already submitted.
+    pub async fn handle_update(&self, action_type: &str, action: SatoruAction) {
+        self.drop_pending(action_type, &action.key).await;
+        if action.is_frozen == Some(true) {
+            info!("{} {} is frozen, not executing it", action_type, action.key);
+            return;
+        }
+        match has_live_execution(&self.pool, &action.key).await {
+            Ok(true) => {
+                debug!(
+                    "{} {} was already submitted, ignoring its update",
+                    action_type, action.key
+                );
+            }
+            Ok(false) => self.handle_action(action_type, action).await,
+            Err(e) => error!(
+                "Could not check the execution of {} {}: {:?}",
+                action_type, action.key, e
+            ),
+        }
+    }
+
+    // Drops the queued call of an updated or removed action, and its pending retries.
+    pub async fn drop_pending(&self, action_type: &str, action_key: &str) {
+        self.preflight_retries.forget(action_key);
+        if self.batch_executor.remove(action_key).await > 0 {
+            info!("Dropped the queued call of {} {}", action_type, action_key);
+        }
+    }
+
+    // Runs one of the keeper loops until a shutdown is requested or it fails.
+    pub async fn run_mode(self: Arc<Self>, mode: KeeperMode) -> Result<(), KeeperError> {
+        match mode {
+            KeeperMode::Execution => self.execution_mode().await,
+            KeeperMode::Liquidation => self.liquidation_mode().await,
+            KeeperMode::Trigger => self.trigger_mode().await,
+        }
+    }
+
+    async fn execution_mode(self: Arc<Self>) -> Result<(), KeeperError> {
+        let channels: Vec<&str> = vec!["orders_update", "deposits_update", "withdrawals_update"];
+        let keeper = Arc::clone(&self);
+        let call_back = move |payload: Payload| {
+            let keeper = Arc::clone(&keeper);
+            let in_flight = keeper.in_flight.start();
+            task::spawn(async move {
+                let _in_flight = in_flight;
+                match payload.table.as_str() {
+                    "orders" => match payload.action_type {
+                        ActionType::INSERT => {
+                            handle_order(&keeper, payload.row_data).await;
+                        }
+                        ActionType::UPDATE => {
+                            keeper.handle_update("order", payload.row_data).await;
+                        }
+                        ActionType::DELETE => {
+                            keeper.drop_pending("order", &payload.row_data.key).await;
+                        }
+                    },
+                    "deposits" => match payload.action_type {
+                        ActionType::INSERT => {
+                            handle_deposit(&keeper, payload.row_data).await;
+                        }
+                        ActionType::UPDATE => {
+                            keeper.handle_update("deposit", payload.row_data).await;
+                        }
+                        ActionType::DELETE => {
+                            keeper.drop_pending("deposit", &payload.row_data.key).await;
+                        }
+                    },
+                    "withdrawals" => match payload.action_type {
+                        ActionType::INSERT => {
+                            handle_withdrawal(&keeper, payload.row_data).await;
+                        }
+                        ActionType::UPDATE => {
+                            keeper.handle_update("withdrawal", payload.row_data).await;
+                        }
+                        ActionType::DELETE => {
+                            keeper
+                                .drop_pending("withdrawal", &payload.row_data.key)
+                                .await;
+                        }
+                    },
+                    &_ => {}
+                }
+            })
+        };
+        // Actions created while the keeper was not listening, before it started or while
+        // its listener reconnected, have no notification.
+        let keeper = Arc::clone(&self);
+        let on_listen = move || {
+            let keeper = Arc::clone(&keeper);
+            task::spawn(async move { keeper.catch_up().await });
+        };
+        info!("Keeper connected to DB and listening...");
+
+        let listening = start_listening(
+            &self.pool,
+            channels,
+            call_back,
+            on_listen,
+            &self.listener_health,
+        );
+        tokio::select! {
+            _ = listening => Ok(()),
+            _ = self.retry_preflight() => Ok(()),
+            _ = self.shutdown_requested() => Ok(()),
+        }
+    }
+
+    async fn liquidation_mode(self: Arc<Self>) -> Result<(), KeeperError> {
+        info!("Running liquidation mode...");
+
+        let account = self.account();
+        let mut schedule = ScanSchedule::new(self.config.liquidation.clone());
+        let mut trigger = ScanTrigger::Start;
+        while !self.is_shutting_down() {
+            let scan_started = Instant::now();
+            let scan = get_liquidatable_positions(
+                &self.pool,
+                Arc::clone(&account),
+                self.contracts(),
+                self.price_provider.as_ref(),
+                self.config
+                    .liquidation
+                    .precheck
+                    .then_some(self.config.liquidation.precheck_margin_bps),
+            )
+            .await;
+            let scan_duration = scan_started.elapsed();
+            LIQUIDATION_SCANS.inc();
+            LIQUIDATION_SCAN_DURATION_MS.set(scan_duration.as_millis() as i64);
+
+            match scan {
+                Ok(scan) => {
+                    info!(
+                        "Liquidation scan on {:?} took {:?}, {} liquidatable positions",
+                        trigger,
+                        scan_duration,
+                        scan.positions.len()
+                    );
+                    schedule.scanned(scan.block, scan.prices);
+                    execute_liquidations(&self, scan.positions).await;
+                }
+                Err(e) => {
+                    error!(
+                        "Error occured while getting liquidatable positions: {:?}",
+                        e
+                    );
+                    schedule.scanned(None, HashMap::new());
+                }
+            }
+            debug!(
+                "Price cache: {} hits, {} misses",
+                PRICE_CACHE_HITS.get(),
+                PRICE_CACHE_MISSES.get()
+            );
+
+            trigger = tokio::select! {
+                trigger = schedule.wait(account.provider(), self.price_provider.as_ref()) => trigger,
+                _ = self.shutdown_requested() => break,
+            };
+        }
+        Ok(())
+    }
+
+    async fn trigger_mode(self: Arc<Self>) -> Result<(), KeeperError> {
+        info!("Running trigger mode...");
+
+        let account = self.account();
+        // The trigger orders are checked at the pace of the liquidation scans, without
+        // watching prices as the orders have no reference prices.
+        let mut schedule = ScanSchedule::new(self.config.liquidation.clone());
+        while !self.is_shutting_down() {
+            let positions_to_trigger = get_triggerable_orders(
+                &self.pool,
+                Arc::clone(&account),
+                self.price_provider.as_ref(),
+            )
+            .await;
+            match positions_to_trigger {
+                Ok(orders) => {
+                    execute_trigger_positions(&self, orders).await;
+                }
+                Err(e) => {
+                    error!("Error occured while getting trigerrable order: {:?}", e);
+                }
+            }
+            debug!(
+                "Price cache: {} hits, {} misses",
+                PRICE_CACHE_HITS.get(),
+                PRICE_CACHE_MISSES.get()
+            );
+
+            schedule.scanned(None, HashMap::new());
+            let trigger = tokio::select! {
+                trigger = schedule.wait(account.provider(), self.price_provider.as_ref()) => trigger,
+                _ = self.shutdown_requested() => break,
+            };
+            debug!("Trigger orders check on {:?}", trigger);
+        }
+        Ok(())
+    }
+}
+
+fn build_account(
+    config: &KeeperConfig,
+) -> SingleOwnerAccount<JsonRpcClient<HttpTransport>, LocalWallet> {
+    let provider = JsonRpcClient::new(HttpTransport::new(config.rpc_url.clone()));
+    let signer = LocalWallet::from(SigningKey::from_secret_scalar(config.signer_private_key));
+
+    let mut account = SingleOwnerAccount::new(
+        provider,
+        signer,
+        config.account_address,
+        config.network.chain_id(),
+        ExecutionEncoding::New,
+    );
+    account.set_block_id(BlockId::Tag(BlockTag::Pending));
+    account
+}
+
+// Refuses to run against a node of another network than the configured one,
+// transactions signed for one chain id are rejected by the others.
+async fn check_chain_id(
+    config: &KeeperConfig,
+    account: &SingleOwnerAccount<JsonRpcClient<HttpTransport>, LocalWallet>,
+) -> Result<(), KeeperError> {
+    let rpc_chain_id = account
+        .provider()
+        .chain_id()
+        .await
+        .map_err(|e| KeeperError::ProviderUrlError(format!("Could not get chain id: {}", e)))?;
+    if rpc_chain_id != config.network.chain_id() {
+        return Err(KeeperError::ConfigError(format!(
+            "The RPC is on chain {} but the keeper is configured for {} ({})",
+            chain_id_name(rpc_chain_id),
+            config.network,
+            chain_id_name(config.network.chain_id())
+        )));
+    }
+    info!(
+        "Connected to {} ({})",
+        config.network,
+        chain_id_name(rpc_chain_id)
+    );
+    Ok(())
+}
+
+// The revert reason of a simulated transaction, None when its execution succeeds.
+pub fn revert_reason(simulation: &SimulatedTransaction) -> Option<String> {
+    match &simulation.transaction_trace {
+        TransactionTrace::Invoke(trace) => match &trace.execute_invocation {
+            ExecuteInvocation::Reverted(reverted) => Some(reverted.revert_reason.clone()),
+            ExecuteInvocation::Success(_) => None,
+        },
+        _ => None,
+    }
+}
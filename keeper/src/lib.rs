@@ -1,8 +1,23 @@
+pub mod batch;
+pub mod catchup;
+pub mod claim;
+pub mod config;
 pub mod error;
+pub mod health;
+pub mod keeper;
 pub mod liquidation;
 pub mod listen_db;
+pub mod metrics;
+pub mod nonce;
+pub mod preflight;
 pub mod price;
 pub mod query;
+pub mod supervisor;
+pub mod token;
+pub mod tracker;
 pub mod trade;
 pub mod trigger;
 pub mod types;
+
+pub use config::{KeeperConfig, KeeperConfigBuilder};
+pub use keeper::{Keeper, KeeperMode};
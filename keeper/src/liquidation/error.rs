@@ -4,4 +4,16 @@ use thiserror::Error;
 pub enum LiquidationError {
     #[error("Failed to call is_liquidatable")]
     IsLiquidatableCallFailed(),
+    #[error("env variable is not set")]
+    EnvVarNotSet(String),
+    #[error("Conversion Error")]
+    ConversionError(String),
+    #[error("Smart Contract Error")]
+    SmartContractError(String),
+    #[error("Price Error")]
+    PriceError(String),
+    #[error("Database Error")]
+    DatabaseError(String),
+    #[error("Market value is not set in the DataStore")]
+    MissingMarketValue(String),
 }
@@ -0,0 +1,94 @@
+use std::{sync::Arc, vec};
+
+use cainome::rs::abigen;
+use log::{debug, error};
+use starknet::{
+    accounts::{Call, SingleOwnerAccount},
+    providers::jsonrpc::{HttpTransport, JsonRpcClient},
+    signers::LocalWallet,
+};
+
+use crate::{
+    config::ContractAddresses,
+    error::KeeperError,
+    keeper::Keeper,
+    price::provider::PriceProvider,
+    trade::utils::oracle_params_setup,
+    types::{DataStore, Market, Position},
+};
+
+use super::error::LiquidationError;
+
+abigen!(
+    LiquidationHandler,
+    "./resources/satoru_LiquidationHandler.abi.json",
+);
+
+crate::impl_from_oracle_params!(SetPricesParams);
+
+// Sends a liquidation for every position reported as liquidatable.
+// A position that cannot be liquidated is logged and does not stop the others,
+// the remaining positions are left for the next scan on shutdown.
+pub async fn execute_liquidations(keeper: &Keeper, positions: Vec<Position>) {
+    for position in positions {
+        if keeper.is_shutting_down() {
+            break;
+        }
+        let position_key = position.key;
+        match keeper.liquidate(position).await {
+            Ok(_) => {}
+            Err(KeeperError::ActionClaimed(_)) => {
+                debug!(
+                    "Position {:#x} is liquidated by another keeper",
+                    position_key
+                );
+            }
+            Err(KeeperError::SimulationReverted(reason)) => {
+                debug!(
+                    "Position {:#x} left for the next scan: {}",
+                    position_key, reason
+                );
+            }
+            Err(e) => error!("Failed to liquidate position {:#x}: {:?}", position_key, e),
+        }
+    }
+}
+
+pub async fn get_execute_liquidation_call(
+    position: Position,
+    account: Arc<SingleOwnerAccount<JsonRpcClient<HttpTransport>, LocalWallet>>,
+    contracts: &ContractAddresses,
+    price_provider: &dyn PriceProvider,
+) -> Result<Call, LiquidationError> {
+    let liquidation_handler =
+        LiquidationHandler::new(contracts.liquidation_handler, account.clone());
+    let data_store = DataStore::new(contracts.data_store, account.clone());
+
+    let market_datastore = data_store
+        .get_market(&position.market)
+        .call()
+        .await
+        .map_err(|e| {
+            LiquidationError::SmartContractError(format!("Could not get market: {}", e))
+        })?;
+    let market: Market = Market {
+        long_token: market_datastore.long_token,
+        market_token: market_datastore.market_token,
+        index_token: market_datastore.index_token,
+        short_token: market_datastore.short_token,
+    };
+
+    let set_prices_params: SetPricesParams =
+        oracle_params_setup(account.clone(), price_provider, None, vec![market])
+            .await
+            .map_err(|e| LiquidationError::PriceError(format!("{:?}", e)))?
+            .into();
+
+    Ok(liquidation_handler.execute_liquidation_getcall(
+        &position.account,
+        &position.market,
+        &position.collateral_token,
+        &position.is_long,
+        &set_prices_params,
+    ))
+}
@@ -0,0 +1,265 @@
+use starknet::core::{types::FieldElement, utils::cairo_short_string_to_felt};
+use starknet_crypto::poseidon_hash_many;
+
+// The DataStore keys of the market values read by the liquidation pre-check, derived
+// like the Satoru keys: the poseidon hash of the key name followed by its arguments.
+// The names are the short strings of keys.cairo, shortened there to fit in a felt.
+
+const MIN_COLLATERAL_USD: &str = "MIN_COLLATERAL_USD";
+const MIN_COLLATERAL_FACTOR: &str = "MIN_COLLATERAL_FACTOR";
+const MAX_POSITION_IMPACT_FACTOR_FOR_LIQUIDATIONS: &str = "MAX_POS_IMP_FACT_FOR_LIQ";
+const POOL_AMOUNT: &str = "POOL_AMOUNT";
+const MAX_PNL_FACTOR: &str = "MAX_PNL_FACTOR";
+const MAX_PNL_FACTOR_FOR_TRADERS: &str = "MAX_PNL_FACT_FOR_TRADERS";
+const OPEN_INTEREST: &str = "OPEN_INTEREST";
+const OPEN_INTEREST_IN_TOKENS: &str = "OPEN_INTEREST_IN_TOKENS";
+const CUMULATIVE_BORROWING_FACTOR: &str = "CUMULATIVE_BORROWING_FACTOR";
+const FUNDING_FEE_AMOUNT_PER_SIZE: &str = "FUNDING_FEE_AMT_PER_SIZE";
+const POSITION_FEE_FACTOR: &str = "POSITION_FEE_FACTOR";
+
+pub fn min_collateral_usd_key() -> FieldElement {
+    key_name(MIN_COLLATERAL_USD)
+}
+
+pub fn min_collateral_factor_key(market: FieldElement) -> FieldElement {
+    poseidon_hash_many(&[key_name(MIN_COLLATERAL_FACTOR), market])
+}
+
+pub fn max_position_impact_factor_for_liquidations_key(market: FieldElement) -> FieldElement {
+    poseidon_hash_many(&[
+        key_name(MAX_POSITION_IMPACT_FACTOR_FOR_LIQUIDATIONS),
+        market,
+    ])
+}
+
+pub fn pool_amount_key(market: FieldElement, token: FieldElement) -> FieldElement {
+    poseidon_hash_many(&[key_name(POOL_AMOUNT), market, token])
+}
+
+pub fn max_pnl_factor_for_traders_key(market: FieldElement, is_long: bool) -> FieldElement {
+    poseidon_hash_many(&[
+        key_name(MAX_PNL_FACTOR),
+        key_name(MAX_PNL_FACTOR_FOR_TRADERS),
+        market,
+        bool_felt(is_long),
+    ])
+}
+
+pub fn open_interest_key(
+    market: FieldElement,
+    collateral_token: FieldElement,
+    is_long: bool,
+) -> FieldElement {
+    poseidon_hash_many(&[
+        key_name(OPEN_INTEREST),
+        market,
+        collateral_token,
+        bool_felt(is_long),
+    ])
+}
+
+pub fn open_interest_in_tokens_key(
+    market: FieldElement,
+    collateral_token: FieldElement,
+    is_long: bool,
+) -> FieldElement {
+    poseidon_hash_many(&[
+        key_name(OPEN_INTEREST_IN_TOKENS),
+        market,
+        collateral_token,
+        bool_felt(is_long),
+    ])
+}
+
+pub fn cumulative_borrowing_factor_key(market: FieldElement, is_long: bool) -> FieldElement {
+    poseidon_hash_many(&[
+        key_name(CUMULATIVE_BORROWING_FACTOR),
+        market,
+        bool_felt(is_long),
+    ])
+}
+
+pub fn funding_fee_amount_per_size_key(
+    market: FieldElement,
+    collateral_token: FieldElement,
+    is_long: bool,
+) -> FieldElement {
+    poseidon_hash_many(&[
+        key_name(FUNDING_FEE_AMOUNT_PER_SIZE),
+        market,
+        collateral_token,
+        bool_felt(is_long),
+    ])
+}
+
+pub fn position_fee_factor_key(market: FieldElement, for_positive_impact: bool) -> FieldElement {
+    poseidon_hash_many(&[
+        key_name(POSITION_FEE_FACTOR),
+        market,
+        bool_felt(for_positive_impact),
+    ])
+}
+
+fn key_name(name: &str) -> FieldElement {
+    cairo_short_string_to_felt(name).expect("Key names are cairo short strings")
+}
+
+fn bool_felt(value: bool) -> FieldElement {
+    if value {
+        FieldElement::ONE
+    } else {
+        FieldElement::ZERO
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashSet, fs};
+
+    use super::*;
+
+    const KEY_NAMES: [&str; 11] = [
+        MIN_COLLATERAL_USD,
+        MIN_COLLATERAL_FACTOR,
+        MAX_POSITION_IMPACT_FACTOR_FOR_LIQUIDATIONS,
+        POOL_AMOUNT,
+        MAX_PNL_FACTOR,
+        MAX_PNL_FACTOR_FOR_TRADERS,
+        OPEN_INTEREST,
+        OPEN_INTEREST_IN_TOKENS,
+        CUMULATIVE_BORROWING_FACTOR,
+        FUNDING_FEE_AMOUNT_PER_SIZE,
+        POSITION_FEE_FACTOR,
+    ];
+
+    fn felt(hex: &str) -> FieldElement {
+        FieldElement::from_hex_be(hex).unwrap()
+    }
+
+    // The felt constants of the Satoru Reader program, the key names it reads from the
+    // DataStore are among them.
+    fn satoru_reader_constants() -> HashSet<FieldElement> {
+        let class = fs::read_to_string(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/resources/satoru_Reader.contract_class.json"
+        ))
+        .unwrap();
+        let class: serde_json::Value = serde_json::from_str(&class).unwrap();
+        class["sierra_program"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|value| felt(value.as_str().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn test_key_names_are_satoru_key_names() {
+        let constants = satoru_reader_constants();
+        for name in KEY_NAMES {
+            assert!(
+                constants.contains(&key_name(name)),
+                "{} is not a key read by the Satoru Reader",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn test_keys_are_pinned() {
+        let market = felt("0x123");
+        let token = felt("0x456");
+
+        // The key names of keys.cairo, as felts.
+        assert_eq!(
+            min_collateral_usd_key(),
+            felt("0x4d494e5f434f4c4c41544552414c5f555344")
+        );
+        assert_eq!(
+            min_collateral_factor_key(market),
+            poseidon_hash_many(&[felt("0x4d494e5f434f4c4c41544552414c5f464143544f52"), market])
+        );
+        assert_eq!(
+            max_position_impact_factor_for_liquidations_key(market),
+            poseidon_hash_many(&[
+                felt("0x4d41585f504f535f494d505f464143545f464f525f4c4951"),
+                market
+            ])
+        );
+        assert_eq!(
+            pool_amount_key(market, token),
+            poseidon_hash_many(&[felt("0x504f4f4c5f414d4f554e54"), market, token])
+        );
+        assert_eq!(
+            max_pnl_factor_for_traders_key(market, true),
+            poseidon_hash_many(&[
+                felt("0x4d41585f504e4c5f464143544f52"),
+                felt("0x4d41585f504e4c5f464143545f464f525f54524144455253"),
+                market,
+                FieldElement::ONE
+            ])
+        );
+        assert_eq!(
+            open_interest_key(market, token, false),
+            poseidon_hash_many(&[
+                felt("0x4f50454e5f494e544552455354"),
+                market,
+                token,
+                FieldElement::ZERO
+            ])
+        );
+        assert_eq!(
+            open_interest_in_tokens_key(market, token, true),
+            poseidon_hash_many(&[
+                felt("0x4f50454e5f494e5445524553545f494e5f544f4b454e53"),
+                market,
+                token,
+                FieldElement::ONE
+            ])
+        );
+        assert_eq!(
+            cumulative_borrowing_factor_key(market, false),
+            poseidon_hash_many(&[
+                felt("0x43554d554c41544956455f424f52524f57494e475f464143544f52"),
+                market,
+                FieldElement::ZERO
+            ])
+        );
+        assert_eq!(
+            funding_fee_amount_per_size_key(market, token, true),
+            poseidon_hash_many(&[
+                felt("0x46554e44494e475f4645455f414d545f5045525f53495a45"),
+                market,
+                token,
+                FieldElement::ONE
+            ])
+        );
+        assert_eq!(
+            position_fee_factor_key(market, false),
+            poseidon_hash_many(&[
+                felt("0x504f534954494f4e5f4645455f464143544f52"),
+                market,
+                FieldElement::ZERO
+            ])
+        );
+    }
+
+    #[test]
+    fn test_keys_depend_on_their_arguments() {
+        let market = FieldElement::from(0x123_u32);
+        let token = FieldElement::from(0x456_u32);
+
+        assert_ne!(
+            open_interest_key(market, token, true),
+            open_interest_key(market, token, false)
+        );
+        assert_ne!(
+            open_interest_key(market, token, true),
+            open_interest_in_tokens_key(market, token, true)
+        );
+        assert_ne!(
+            pool_amount_key(market, token),
+            pool_amount_key(token, market)
+        );
+    }
+}
@@ -1,3 +1,8 @@
 pub mod error;
+pub mod execution;
+pub mod keys;
+pub mod params;
+pub mod precheck;
 pub mod process;
+pub mod schedule;
 pub mod utils;
@@ -0,0 +1,146 @@
+use std::sync::Arc;
+
+use ethnum::U256;
+use starknet::{
+    accounts::SingleOwnerAccount,
+    core::types::FieldElement,
+    providers::jsonrpc::{HttpTransport, JsonRpcClient},
+    signers::LocalWallet,
+};
+
+use super::{
+    error::LiquidationError,
+    keys::{
+        cumulative_borrowing_factor_key, funding_fee_amount_per_size_key,
+        max_pnl_factor_for_traders_key, max_position_impact_factor_for_liquidations_key,
+        min_collateral_factor_key, min_collateral_usd_key, open_interest_in_tokens_key,
+        open_interest_key, pool_amount_key, position_fee_factor_key,
+    },
+    precheck::{to_u256, MarketParams, SideParams},
+};
+use crate::types::{DataStore, Market};
+
+type KeeperDataStore =
+    DataStore<Arc<SingleOwnerAccount<JsonRpcClient<HttpTransport>, LocalWallet>>>;
+
+// Reads the values of a market used by the liquidation pre-check.
+// The factors every market sets are required, a zero means the key is wrong or the market
+// is not configured, the pre-check would then be meaningless.
+// @data_store: The DataStore contract.
+// @market: The market whose values are read.
+pub async fn get_market_params(
+    data_store: &KeeperDataStore,
+    market: &Market,
+) -> Result<MarketParams, LiquidationError> {
+    let market_token = market.market_token.0;
+    Ok(MarketParams {
+        min_collateral_usd: get_u256(data_store, min_collateral_usd_key()).await?,
+        min_collateral_factor: get_required_u256(
+            data_store,
+            min_collateral_factor_key(market_token),
+            "min collateral factor",
+        )
+        .await?,
+        max_position_impact_factor_for_liquidations: get_u256(
+            data_store,
+            max_position_impact_factor_for_liquidations_key(market_token),
+        )
+        .await?,
+        // The close fee is bounded by the larger of the two factors.
+        position_fee_factor: get_u256(data_store, position_fee_factor_key(market_token, true))
+            .await?
+            .max(get_u256(data_store, position_fee_factor_key(market_token, false)).await?),
+        long: get_side_params(data_store, market, true).await?,
+        short: get_side_params(data_store, market, false).await?,
+    })
+}
+
+async fn get_side_params(
+    data_store: &KeeperDataStore,
+    market: &Market,
+    is_long: bool,
+) -> Result<SideParams, LiquidationError> {
+    let market_token = market.market_token.0;
+    let long_token = market.long_token.0;
+    let short_token = market.short_token.0;
+    // When the long and short tokens are the same, both sides share one pool and the
+    // loop below reads the same open interest twice, both are halved.
+    let divisor = if long_token == short_token {
+        U256::new(2)
+    } else {
+        U256::ONE
+    };
+    let pnl_token = if is_long { long_token } else { short_token };
+
+    let pool_amount = get_u256(data_store, pool_amount_key(market_token, pnl_token)).await?;
+    let mut open_interest = U256::ZERO;
+    let mut open_interest_in_tokens = U256::ZERO;
+    for collateral_token in [long_token, short_token] {
+        open_interest += get_u256(
+            data_store,
+            open_interest_key(market_token, collateral_token, is_long),
+        )
+        .await?;
+        open_interest_in_tokens += get_u256(
+            data_store,
+            open_interest_in_tokens_key(market_token, collateral_token, is_long),
+        )
+        .await?;
+    }
+
+    Ok(SideParams {
+        pool_amount: pool_amount / divisor,
+        max_pnl_factor_for_traders: get_required_u256(
+            data_store,
+            max_pnl_factor_for_traders_key(market_token, is_long),
+            "max pnl factor for traders",
+        )
+        .await?,
+        open_interest: open_interest / divisor,
+        open_interest_in_tokens: open_interest_in_tokens / divisor,
+        cumulative_borrowing_factor: get_u256(
+            data_store,
+            cumulative_borrowing_factor_key(market_token, is_long),
+        )
+        .await?,
+        long_token_funding_fee_amount_per_size: get_u256(
+            data_store,
+            funding_fee_amount_per_size_key(market_token, long_token, is_long),
+        )
+        .await?,
+        short_token_funding_fee_amount_per_size: get_u256(
+            data_store,
+            funding_fee_amount_per_size_key(market_token, short_token, is_long),
+        )
+        .await?,
+    })
+}
+
+async fn get_required_u256(
+    data_store: &KeeperDataStore,
+    key: FieldElement,
+    name: &str,
+) -> Result<U256, LiquidationError> {
+    let value = get_u256(data_store, key).await?;
+    if value == U256::ZERO {
+        return Err(LiquidationError::MissingMarketValue(format!(
+            "{} {:#x} is zero",
+            name, key
+        )));
+    }
+    Ok(value)
+}
+
+async fn get_u256(
+    data_store: &KeeperDataStore,
+    key: FieldElement,
+) -> Result<U256, LiquidationError> {
+    data_store
+        .get_u256(&key)
+        .call()
+        .await
+        .map(|value| to_u256(&value))
+        .map_err(|e| {
+            LiquidationError::SmartContractError(format!("Could not get u256 {:#x}: {}", key, e))
+        })
+}
@@ -0,0 +1,483 @@
+use cainome::cairo_serde::U256 as CairoU256;
+use ethnum::{I256, U256};
+
+use crate::types::{Market, MarketPrices, Position, PriceReader};
+
+// Factors stored in the DataStore are scaled by 10^30, as are usd values.
+pub const FLOAT_PRECISION: U256 = U256::new(1_000_000_000_000_000_000_000_000_000_000);
+// Funding fee amounts per size are stored with this extra precision.
+const FLOAT_PRECISION_SQRT: U256 = U256::new(1_000_000_000_000_000);
+// Basis points in one.
+const BPS: u128 = 10_000;
+
+// The market values the liquidation pre-check reads from the DataStore.
+// @min_collateral_usd: The least collateral a position may keep, in usd.
+// @min_collateral_factor: The least collateral a position may keep, relative to its size.
+// @max_position_impact_factor_for_liquidations: The most negative price impact a
+// liquidation may have, relative to the position size.
+// @position_fee_factor: The fee of closing a position, relative to its size.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MarketParams {
+    pub min_collateral_usd: U256,
+    pub min_collateral_factor: U256,
+    pub max_position_impact_factor_for_liquidations: U256,
+    pub position_fee_factor: U256,
+    pub long: SideParams,
+    pub short: SideParams,
+}
+
+// The values of one side of a market, used to cap the pnl of its positions.
+// @pool_amount: The amount of the token backing the pnl, the long token for the long side
+// and the short token for the short side.
+// @open_interest: The open interest of the side in usd.
+// @open_interest_in_tokens: The open interest of the side in index tokens.
+// @cumulative_borrowing_factor: The borrowing factor accrued by the side, as of its last
+// update.
+// @long_token_funding_fee_amount_per_size: The funding fee accrued by the positions of the
+// side with the long token as collateral, as of its last update.
+// @short_token_funding_fee_amount_per_size: The same with the short token as collateral.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SideParams {
+    pub pool_amount: U256,
+    pub max_pnl_factor_for_traders: U256,
+    pub open_interest: U256,
+    pub open_interest_in_tokens: U256,
+    pub cumulative_borrowing_factor: U256,
+    pub long_token_funding_fee_amount_per_size: U256,
+    pub short_token_funding_fee_amount_per_size: U256,
+}
+
+// The outcome of the native liquidation check of a position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrecheckResult {
+    // Liquidatable according to the native estimate, with the reason.
+    Liquidatable(&'static str),
+    // Healthy, but close enough to the threshold that fees could make it liquidatable.
+    NearThreshold,
+    Healthy,
+}
+
+impl PrecheckResult {
+    // Whether the position must be checked with the Reader.
+    pub fn needs_confirmation(&self) -> bool {
+        *self != PrecheckResult::Healthy
+    }
+}
+
+// Estimates whether a position is liquidatable, like Reader.is_position_liquidable but
+// without any RPC call.
+// The price impact of the liquidation is taken at its cap. The borrowing and funding fees
+// accrued as of the last market update and the close fee are deducted from the collateral,
+// the fees accrued since the last update are left to the margin: the positions whose
+// remaining collateral is within margin_bps of their size above the threshold are reported
+// as NearThreshold so they are confirmed on chain.
+// @position: The position to check.
+// @market: The market of the position.
+// @prices: The prices of the market tokens, per unit of token with 30 decimals.
+// @params: The market values read from the DataStore.
+// @margin_bps: The margin above the threshold, in basis points of the position size.
+// @should_validate_min_collateral_usd: Whether the collateral must exceed MIN_COLLATERAL_USD.
+pub fn precheck_position(
+    position: &Position,
+    market: &Market,
+    prices: &MarketPrices,
+    params: &MarketParams,
+    margin_bps: u32,
+    should_validate_min_collateral_usd: bool,
+) -> PrecheckResult {
+    let size_in_usd = to_u256(&position.size_in_usd);
+    let position_pnl_usd = get_position_pnl_usd(position, prices, params);
+
+    let collateral_token_price = if position.collateral_token == market.long_token {
+        &prices.long_token_price
+    } else {
+        &prices.short_token_price
+    };
+    let collateral_usd =
+        to_u256(&position.collateral_amount).saturating_mul(to_u256(&collateral_token_price.min));
+    let fees_usd = get_position_fees_usd(position, market, collateral_token_price, params);
+
+    // Liquidations get no positive price impact and their negative impact is capped,
+    // the cap is the worst case.
+    let price_impact_usd = -apply_factor(
+        size_in_usd,
+        params.max_position_impact_factor_for_liquidations,
+    )
+    .as_i256();
+
+    let remaining_collateral_usd =
+        collateral_usd.as_i256() + position_pnl_usd + price_impact_usd - fees_usd.as_i256();
+
+    if should_validate_min_collateral_usd
+        && remaining_collateral_usd < params.min_collateral_usd.as_i256()
+    {
+        return PrecheckResult::Liquidatable("min collateral");
+    }
+    if remaining_collateral_usd <= I256::ZERO {
+        return PrecheckResult::Liquidatable("no collateral left");
+    }
+
+    let min_collateral_usd_for_leverage = apply_factor(size_in_usd, params.min_collateral_factor);
+    if remaining_collateral_usd <= min_collateral_usd_for_leverage.as_i256() {
+        return PrecheckResult::Liquidatable("min collateral for leverage");
+    }
+
+    let mut threshold = min_collateral_usd_for_leverage;
+    if should_validate_min_collateral_usd {
+        threshold = threshold.max(params.min_collateral_usd);
+    }
+    let margin = mul_div(size_in_usd, U256::from(margin_bps), U256::from(BPS));
+    if remaining_collateral_usd <= threshold.saturating_add(margin).as_i256() {
+        return PrecheckResult::NearThreshold;
+    }
+    PrecheckResult::Healthy
+}
+
+// The fees a position pays when liquidated, in usd: the borrowing and funding fees accrued
+// since the position was last updated and the close fee.
+// The funding fee, paid in collateral tokens, is valued at the max price so it is not
+// underestimated.
+pub fn get_position_fees_usd(
+    position: &Position,
+    market: &Market,
+    collateral_token_price: &PriceReader,
+    params: &MarketParams,
+) -> U256 {
+    let size_in_usd = to_u256(&position.size_in_usd);
+    let side = if position.is_long {
+        &params.long
+    } else {
+        &params.short
+    };
+
+    let borrowing_factor = side
+        .cumulative_borrowing_factor
+        .saturating_sub(to_u256(&position.borrowing_factor));
+    let borrowing_fee_usd = apply_factor(size_in_usd, borrowing_factor);
+
+    let funding_fee_amount_per_size = if position.collateral_token == market.long_token {
+        side.long_token_funding_fee_amount_per_size
+    } else {
+        side.short_token_funding_fee_amount_per_size
+    };
+    let funding_fee_amount = mul_div(
+        size_in_usd,
+        funding_fee_amount_per_size.saturating_sub(to_u256(&position.funding_fee_amount_per_size)),
+        FLOAT_PRECISION.saturating_mul(FLOAT_PRECISION_SQRT),
+    );
+    let funding_fee_usd = funding_fee_amount.saturating_mul(to_u256(&collateral_token_price.max));
+
+    let close_fee_usd = apply_factor(size_in_usd, params.position_fee_factor);
+    borrowing_fee_usd
+        .saturating_add(funding_fee_usd)
+        .saturating_add(close_fee_usd)
+}
+
+// The pnl of a whole position in usd, capped like the pnl of the pool when the pool pnl
+// exceeds the max pnl factor for traders.
+pub fn get_position_pnl_usd(
+    position: &Position,
+    prices: &MarketPrices,
+    params: &MarketParams,
+) -> I256 {
+    let size_in_usd = to_u256(&position.size_in_usd).as_i256();
+    let execution_price = pick_price_for_pnl(&prices.index_token_price, position.is_long, false);
+    let position_value = to_u256(&position.size_in_tokens)
+        .saturating_mul(execution_price)
+        .as_i256();
+
+    let total_position_pnl = if position.is_long {
+        position_value - size_in_usd
+    } else {
+        size_in_usd - position_value
+    };
+    if total_position_pnl <= I256::ZERO {
+        return total_position_pnl;
+    }
+
+    let (side, pool_token_price) = if position.is_long {
+        (&params.long, &prices.long_token_price)
+    } else {
+        (&params.short, &prices.short_token_price)
+    };
+    let pool_token_usd = side
+        .pool_amount
+        .saturating_mul(to_u256(&pool_token_price.min));
+    let pool_pnl = get_pnl(side, &prices.index_token_price, position.is_long);
+    let capped_pool_pnl = get_capped_pnl(pool_pnl, pool_token_usd, side.max_pnl_factor_for_traders);
+    if capped_pool_pnl != pool_pnl && capped_pool_pnl > I256::ZERO && pool_pnl > I256::ZERO {
+        return mul_div(
+            total_position_pnl.as_u256(),
+            capped_pool_pnl.as_u256(),
+            pool_pnl.as_u256(),
+        )
+        .as_i256();
+    }
+    total_position_pnl
+}
+
+// The pnl of the open interest of one side of a market, maximized.
+fn get_pnl(side: &SideParams, index_token_price: &PriceReader, is_long: bool) -> I256 {
+    let price = pick_price_for_pnl(index_token_price, is_long, true);
+    let open_interest_value = side.open_interest_in_tokens.saturating_mul(price).as_i256();
+    let open_interest = side.open_interest.as_i256();
+    if is_long {
+        open_interest_value - open_interest
+    } else {
+        open_interest - open_interest_value
+    }
+}
+
+// A positive pnl is capped to a factor of the pool value.
+fn get_capped_pnl(pnl: I256, pool_usd: U256, max_pnl_factor: U256) -> I256 {
+    if pnl <= I256::ZERO {
+        return pnl;
+    }
+    pnl.min(apply_factor(pool_usd, max_pnl_factor).as_i256())
+}
+
+// The price giving the highest pnl when maximize is set, the lowest otherwise.
+fn pick_price_for_pnl(price: &PriceReader, is_long: bool, maximize: bool) -> U256 {
+    if is_long == maximize {
+        to_u256(&price.max)
+    } else {
+        to_u256(&price.min)
+    }
+}
+
+pub fn apply_factor(value: U256, factor: U256) -> U256 {
+    mul_div(value, factor, FLOAT_PRECISION)
+}
+
+fn mul_div(value: U256, numerator: U256, denominator: U256) -> U256 {
+    if denominator == U256::ZERO {
+        return U256::ZERO;
+    }
+    match value.checked_mul(numerator) {
+        Some(product) => product / denominator,
+        None => (value / denominator).saturating_mul(numerator),
+    }
+}
+
+pub fn to_u256(value: &CairoU256) -> U256 {
+    U256::from_words(value.high, value.low)
+}
+
+#[cfg(test)]
+mod tests {
+    use cainome::cairo_serde::ContractAddress;
+    use starknet::core::types::FieldElement;
+
+    use super::*;
+    use crate::price::{
+        oracle_params::TokenOraclePrice,
+        utils::{to_float_market_prices, MarketTokenPrices},
+    };
+
+    const ETH: u128 = 1_000_000_000_000_000_000;
+    const USDC: u128 = 1_000_000;
+
+    fn usd(dollars: u128) -> U256 {
+        U256::new(dollars) * FLOAT_PRECISION
+    }
+
+    fn cairo(value: U256) -> CairoU256 {
+        let (high, low) = value.into_words();
+        CairoU256 { low, high }
+    }
+
+    // The price of one unit of a token with the given decimals.
+    fn price(dollars: u128, unit: u128) -> PriceReader {
+        let price = cairo(usd(dollars) / U256::new(unit));
+        PriceReader {
+            min: price,
+            max: price,
+        }
+    }
+
+    fn market() -> Market {
+        Market {
+            market_token: ContractAddress::from(FieldElement::ONE),
+            index_token: ContractAddress::from(FieldElement::TWO),
+            long_token: ContractAddress::from(FieldElement::TWO),
+            short_token: ContractAddress::from(FieldElement::THREE),
+        }
+    }
+
+    fn prices(eth_price: u128) -> MarketPrices {
+        MarketPrices {
+            index_token_price: price(eth_price, ETH),
+            long_token_price: price(eth_price, ETH),
+            short_token_price: price(1, USDC),
+        }
+    }
+
+    fn params() -> MarketParams {
+        let side = SideParams {
+            pool_amount: U256::new(10 * ETH),
+            max_pnl_factor_for_traders: FLOAT_PRECISION / 2,
+            open_interest: usd(100_000),
+            open_interest_in_tokens: U256::new(40 * ETH),
+            cumulative_borrowing_factor: U256::ZERO,
+            long_token_funding_fee_amount_per_size: U256::ZERO,
+            short_token_funding_fee_amount_per_size: U256::ZERO,
+        };
+        MarketParams {
+            min_collateral_usd: usd(5),
+            min_collateral_factor: FLOAT_PRECISION / 100,
+            max_position_impact_factor_for_liquidations: FLOAT_PRECISION / 200,
+            position_fee_factor: U256::ZERO,
+            long: side.clone(),
+            short: side,
+        }
+    }
+
+    // A 10x long of 4 ETH opened at 2500 with 1000 USDC of collateral.
+    fn long_position() -> Position {
+        Position {
+            key: FieldElement::ONE,
+            account: ContractAddress::from(FieldElement::ONE),
+            market: ContractAddress::from(FieldElement::ONE),
+            collateral_token: ContractAddress::from(FieldElement::THREE),
+            size_in_usd: cairo(usd(10_000)),
+            size_in_tokens: cairo(U256::new(4 * ETH)),
+            collateral_amount: cairo(U256::new(1_000 * USDC)),
+            borrowing_factor: cairo(U256::ZERO),
+            funding_fee_amount_per_size: cairo(U256::ZERO),
+            long_token_claimable_funding_amount_per_size: cairo(U256::ZERO),
+            short_token_claimable_funding_amount_per_size: cairo(U256::ZERO),
+            increased_at_block: 0,
+            decreased_at_block: 0,
+            is_long: true,
+        }
+    }
+
+    #[test]
+    fn test_precheck_position() {
+        let check = |eth_price, validate_min_collateral_usd| {
+            precheck_position(
+                &long_position(),
+                &market(),
+                &prices(eth_price),
+                &params(),
+                100,
+                validate_min_collateral_usd,
+            )
+        };
+
+        // 1000 of collateral, 50 of price impact, 100 required for the leverage and 100 of margin.
+        assert_eq!(check(2500, true), PrecheckResult::Healthy);
+        assert_eq!(check(2290, true), PrecheckResult::NearThreshold);
+        assert_eq!(
+            check(2270, true),
+            PrecheckResult::Liquidatable("min collateral for leverage")
+        );
+        assert_eq!(
+            check(2260, true),
+            PrecheckResult::Liquidatable("min collateral")
+        );
+        assert_eq!(
+            check(2260, false),
+            PrecheckResult::Liquidatable("no collateral left")
+        );
+        assert!(!check(2500, true).needs_confirmation());
+    }
+
+    #[test]
+    fn test_fees_make_position_liquidatable() {
+        let check = |params: &MarketParams| {
+            precheck_position(
+                &long_position(),
+                &market(),
+                &prices(2500),
+                params,
+                100,
+                true,
+            )
+        };
+        assert_eq!(check(&params()), PrecheckResult::Healthy);
+
+        // 900 of borrowing fee, 9% of the 10000 position accrued since it was opened.
+        let mut borrowing = params();
+        borrowing.long.cumulative_borrowing_factor = FLOAT_PRECISION * 9 / 100;
+        assert_eq!(
+            check(&borrowing),
+            PrecheckResult::Liquidatable("min collateral for leverage")
+        );
+
+        // 900 USDC of funding fee.
+        let mut funding = params();
+        funding.long.short_token_funding_fee_amount_per_size =
+            U256::new(900 * USDC) * FLOAT_PRECISION * FLOAT_PRECISION_SQRT / usd(10_000);
+        assert_eq!(
+            get_position_fees_usd(
+                &long_position(),
+                &market(),
+                &prices(2500).short_token_price,
+                &funding
+            ),
+            usd(900)
+        );
+        assert_eq!(
+            check(&funding),
+            PrecheckResult::Liquidatable("min collateral for leverage")
+        );
+
+        // A close fee of 0.5% leaves the position healthy, one of 8% brings it within
+        // the margin.
+        let mut close_fee = params();
+        close_fee.position_fee_factor = FLOAT_PRECISION / 200;
+        assert_eq!(check(&close_fee), PrecheckResult::Healthy);
+        close_fee.position_fee_factor = FLOAT_PRECISION * 8 / 100;
+        assert_eq!(check(&close_fee), PrecheckResult::NearThreshold);
+    }
+
+    // The prices as returned by Pragma, with 8 decimals.
+    fn provider_prices(eth_price: u128) -> MarketTokenPrices {
+        let token_price = |token, price, token_decimals| TokenOraclePrice {
+            token: ContractAddress::from(token),
+            min_price: price * 100_000_000,
+            max_price: price * 100_000_000,
+            price_decimals: 8,
+            token_decimals,
+        };
+        MarketTokenPrices {
+            long_token_price: token_price(FieldElement::TWO, eth_price, 18),
+            short_token_price: token_price(FieldElement::THREE, 1, 6),
+            index_token_price: Some(token_price(FieldElement::TWO, eth_price, 18)),
+        }
+    }
+
+    #[test]
+    fn test_precheck_position_with_provider_prices() {
+        let check = |eth_price| {
+            precheck_position(
+                &long_position(),
+                &market(),
+                &to_float_market_prices(&provider_prices(eth_price)),
+                &params(),
+                100,
+                true,
+            )
+        };
+
+        assert_eq!(check(2500), PrecheckResult::Healthy);
+        assert_eq!(check(2290), PrecheckResult::NearThreshold);
+        assert_eq!(check(2260), PrecheckResult::Liquidatable("min collateral"));
+    }
+
+    #[test]
+    fn test_position_pnl_is_capped() {
+        let position = long_position();
+
+        assert_eq!(
+            get_position_pnl_usd(&position, &prices(2000), &params()),
+            -usd(2_000).as_i256()
+        );
+        // The pool pnl of 20000 is capped to half the 30000 of the pool.
+        assert_eq!(
+            get_position_pnl_usd(&position, &prices(3000), &params()),
+            usd(1_500).as_i256()
+        );
+    }
+}
@@ -1,61 +1,159 @@
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
 use sqlx::PgPool;
 use starknet::{
     accounts::{ConnectedAccount, SingleOwnerAccount},
-    core::types::{BlockId, BlockTag, MaybePendingBlockWithTxHashes},
+    core::types::{BlockId, BlockTag, FieldElement, MaybePendingBlockWithTxHashes},
     providers::{jsonrpc::HttpTransport, JsonRpcClient, Provider},
     signers::LocalWallet,
 };
 
 use crate::{
-    liquidation::utils::is_liquidatable_call,
-    price::utils::get_market_prices,
+    config::ContractAddresses,
+    liquidation::{
+        error::LiquidationError,
+        params::get_market_params,
+        precheck::{precheck_position, MarketParams},
+        utils::is_liquidatable_call,
+    },
+    metrics::LIQUIDATION_PRECHECK_SKIPPED,
+    price::{
+        provider::PriceProvider,
+        utils::{get_market_token_prices, to_float_market_prices},
+    },
     query::get_market,
-    types::{MarketPrices, Position},
+    types::{DataStore, MarketPrices, Position},
 };
 
+// The outcome of a liquidation scan.
+// @positions: The positions the Reader reports as liquidatable.
+// @block: The block the scan ran on, None on a pending block.
+// @prices: The mid price of each token priced in the scan, as returned by the price source.
+#[derive(Debug, Default)]
+pub struct LiquidationScan {
+    pub positions: Vec<Position>,
+    pub block: Option<u64>,
+    pub prices: HashMap<FieldElement, u128>,
+}
+
+// Returns the positions the Reader reports as liquidatable.
+// @precheck_margin_bps: When set, the positions are first estimated off chain and only
+// those within this margin of the threshold are checked with the Reader.
+// A position that cannot be checked is logged and skipped, the next scan checks it again.
 pub async fn get_liquidatable_positions(
     pool: &PgPool,
     account: Arc<SingleOwnerAccount<JsonRpcClient<HttpTransport>, LocalWallet>>,
-) -> Result<Vec<Position>, sqlx::Error> {
+    contracts: &ContractAddresses,
+    price_provider: &dyn PriceProvider,
+    precheck_margin_bps: Option<u32>,
+) -> Result<LiquidationScan, LiquidationError> {
     let positions: Vec<Position> = sqlx::query_as("SELECT * FROM positions")
         .fetch_all(pool)
-        .await?;
+        .await
+        .map_err(|e| LiquidationError::DatabaseError(e.to_string()))?;
 
     let block = Arc::clone(&account)
         .provider()
         .get_block_with_tx_hashes(BlockId::Tag(BlockTag::Latest))
         .await
-        .expect("Could not fetch latest block");
-    let timestamp = match block {
-        MaybePendingBlockWithTxHashes::Block(block) => block.timestamp,
-        MaybePendingBlockWithTxHashes::PendingBlock(block) => block.timestamp,
+        .map_err(|e| {
+            LiquidationError::SmartContractError(format!("Could not fetch latest block: {}", e))
+        })?;
+    let (block, timestamp) = match block {
+        MaybePendingBlockWithTxHashes::Block(block) => (Some(block.block_number), block.timestamp),
+        MaybePendingBlockWithTxHashes::PendingBlock(block) => (None, block.timestamp),
     };
 
-    let mut liquidatable_positions: Vec<Position> = Vec::new();
+    let data_store = DataStore::new(contracts.data_store, Arc::clone(&account));
+    // The market values of the pre-check, read once per scan.
+    let mut market_params: HashMap<FieldElement, MarketParams> = HashMap::new();
+    let mut scan = LiquidationScan {
+        block,
+        ..LiquidationScan::default()
+    };
     for position in positions {
-        let market = get_market(position.clone().market.0.to_string(), pool)
-            .await
-            .expect("Could not get market");
-        let market_prices: MarketPrices = get_market_prices(market.clone(), timestamp.to_string())
-            .await
-            .expect(format!("Could not get market prices for market: :{:?}", market).as_str());
-        let (is_liquidatable, _reason) = is_liquidatable_call(
+        let market = match get_market(position.market.0.to_string(), pool).await {
+            Ok(market) => market,
+            Err(e) => {
+                log::warn!(
+                    "Could not get the market of position {:#x}: {:?}",
+                    position.key,
+                    e
+                );
+                continue;
+            }
+        };
+        let token_prices =
+            match get_market_token_prices(price_provider, &market, timestamp.to_string()).await {
+                Ok(token_prices) => token_prices,
+                Err(e) => {
+                    log::warn!(
+                        "Could not get the prices of market {:#x}: {:?}",
+                        market.market_token.0,
+                        e
+                    );
+                    continue;
+                }
+            };
+        for price in [
+            &token_prices.long_token_price,
+            &token_prices.short_token_price,
+        ]
+        .into_iter()
+        .chain(token_prices.index_token_price.as_ref())
+        {
+            scan.prices.insert(price.token.0, price.mid_price());
+        }
+        // The pre-check and the Reader judge the position on the same prices.
+        let market_prices: MarketPrices = to_float_market_prices(&token_prices);
+
+        if let Some(margin_bps) = precheck_margin_bps {
+            let market_token = market.market_token.0;
+            if !market_params.contains_key(&market_token) {
+                match get_market_params(&data_store, &market).await {
+                    Ok(params) => {
+                        market_params.insert(market_token, params);
+                    }
+                    Err(e) => log::warn!(
+                        "Could not pre-check the positions of market {:#x}: {:?}",
+                        market_token,
+                        e
+                    ),
+                }
+            }
+            if let Some(params) = market_params.get(&market_token) {
+                let precheck =
+                    precheck_position(&position, &market, &market_prices, params, margin_bps, true);
+                if !precheck.needs_confirmation() {
+                    LIQUIDATION_PRECHECK_SKIPPED.inc();
+                    continue;
+                }
+                log::debug!("Position {:#x} pre-check: {:?}", position.key, precheck);
+            }
+        }
+
+        let is_liquidatable = match is_liquidatable_call(
             Arc::clone(&account),
+            contracts,
             position.clone(),
             market,
             market_prices,
             true,
         )
         .await
-        .expect("is_liquidatable_call fail");
+        {
+            Ok((is_liquidatable, _reason)) => is_liquidatable,
+            Err(e) => {
+                log::warn!("Could not check position {:#x}: {:?}", position.key, e);
+                continue;
+            }
+        };
 
-        log::info!("Position {:?} is liquidatable", position.key);
         if is_liquidatable {
-            liquidatable_positions.push(position);
+            log::info!("Position {:?} is liquidatable", position.key);
+            scan.positions.push(position);
         }
     }
 
-    Ok(liquidatable_positions)
+    Ok(scan)
 }
@@ -0,0 +1,248 @@
+use std::{
+    collections::{hash_map::RandomState, HashMap},
+    hash::{BuildHasher, Hasher},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use cainome::cairo_serde::ContractAddress;
+use log::debug;
+use starknet::{
+    core::types::FieldElement,
+    providers::{jsonrpc::HttpTransport, JsonRpcClient, Provider},
+};
+use tokio::time::sleep;
+
+use crate::{config::LiquidationConfig, price::provider::PriceProvider};
+
+// Basis points in one.
+const BPS: u128 = 10_000;
+
+// What started a liquidation scan.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScanTrigger {
+    // The first scan of the loop.
+    Start,
+    NewBlock(u64),
+    Interval,
+    // The price of a token moved more than the threshold since the previous scan.
+    PriceMove(FieldElement),
+}
+
+// Decides when the liquidation mode scans the positions again: on a new block or once
+// the scan interval elapsed, whichever comes first, delayed by a random jitter so that
+// replicas and restarts do not scan in lockstep.
+// New blocks and price moves only bring a scan forward once the min scan interval elapsed,
+// the latest block and the watched prices are not polled before.
+// A price move is measured against the prices the previous scan used.
+pub struct ScanSchedule {
+    config: LiquidationConfig,
+    // When the previous scan ended.
+    last_scan: Instant,
+    // The jitter of the next scan, drawn after each scan.
+    jitter: Duration,
+    last_block: Option<u64>,
+    // Mid price of each watched token in the previous scan.
+    reference_prices: HashMap<FieldElement, u128>,
+}
+
+impl ScanSchedule {
+    pub fn new(config: LiquidationConfig) -> Self {
+        ScanSchedule {
+            jitter: jitter(config.scan_jitter),
+            config,
+            last_scan: Instant::now(),
+            last_block: None,
+            reference_prices: HashMap::new(),
+        }
+    }
+
+    // Records a scan, the next one is timed from now.
+    // @block: The block the scan ran on, if known.
+    // @prices: The mid price of each token the scan priced, later moves are measured
+    // against them.
+    pub fn scanned(&mut self, block: Option<u64>, prices: HashMap<FieldElement, u128>) {
+        self.last_scan = Instant::now();
+        self.jitter = jitter(self.config.scan_jitter);
+        if block.is_some() {
+            self.last_block = block;
+        }
+        self.reference_prices = if self.config.price_move_bps == 0 {
+            HashMap::new()
+        } else {
+            prices
+        };
+    }
+
+    // Waits until the next scan is due and returns what triggered it.
+    // @provider: Polled for the latest block number.
+    // @price_provider: Polled for the prices of the watched tokens.
+    pub async fn wait(
+        &mut self,
+        provider: &JsonRpcClient<HttpTransport>,
+        price_provider: &dyn PriceProvider,
+    ) -> ScanTrigger {
+        sleep(self.min_wait().saturating_sub(self.last_scan.elapsed())).await;
+        loop {
+            let elapsed = self.last_scan.elapsed();
+            let latest_block = match provider.block_number().await {
+                Ok(block) => Some(block),
+                Err(e) => {
+                    debug!("Could not get the latest block number: {}", e);
+                    None
+                }
+            };
+            if let Some(trigger) = self.next_trigger(elapsed, latest_block) {
+                return trigger;
+            }
+            if let Some(token) = self.moved_token(price_provider).await {
+                return ScanTrigger::PriceMove(token);
+            }
+            let remaining = self.max_wait().saturating_sub(self.last_scan.elapsed());
+            sleep(remaining.min(self.config.block_poll_interval)).await;
+        }
+    }
+
+    // The trigger of the next scan, if it is due.
+    // @elapsed: The time since the previous scan.
+    // @latest_block: The latest block, if known.
+    fn next_trigger(
+        &mut self,
+        elapsed: Duration,
+        latest_block: Option<u64>,
+    ) -> Option<ScanTrigger> {
+        if elapsed < self.min_wait() {
+            return None;
+        }
+        if let Some(block) = latest_block {
+            let last_block = self.last_block.replace(block);
+            if last_block.is_some_and(|last_block| block > last_block) {
+                return Some(ScanTrigger::NewBlock(block));
+            }
+        }
+        if elapsed >= self.max_wait() {
+            return Some(ScanTrigger::Interval);
+        }
+        None
+    }
+
+    fn min_wait(&self) -> Duration {
+        self.config.min_scan_interval + self.jitter
+    }
+
+    fn max_wait(&self) -> Duration {
+        self.config.scan_interval + self.jitter
+    }
+
+    // The first watched token whose price moved more than the threshold.
+    async fn moved_token(&self, price_provider: &dyn PriceProvider) -> Option<FieldElement> {
+        let timestamp = now_timestamp();
+        for (token, reference_price) in &self.reference_prices {
+            // A token without a price is skipped, the others may still have moved.
+            let price = match price_provider
+                .get_token_price(ContractAddress::from(*token), &timestamp)
+                .await
+            {
+                Ok(price) => price.mid_price(),
+                Err(e) => {
+                    debug!("Could not watch the price of {:#x}: {:?}", token, e);
+                    continue;
+                }
+            };
+            if moved_bps(*reference_price, price) > u128::from(self.config.price_move_bps) {
+                return Some(*token);
+            }
+        }
+        None
+    }
+}
+
+// How much a price moved from a reference price, in basis points.
+pub fn moved_bps(reference_price: u128, price: u128) -> u128 {
+    if reference_price == 0 {
+        return 0;
+    }
+    reference_price.abs_diff(price).saturating_mul(BPS) / reference_price
+}
+
+fn now_timestamp() -> String {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+        .to_string()
+}
+
+// A random delay between zero and max.
+fn jitter(max: Duration) -> Duration {
+    if max.is_zero() {
+        return Duration::ZERO;
+    }
+    // Every RandomState is seeded differently, which is enough to spread the scans.
+    let random = RandomState::new().build_hasher().finish();
+    Duration::from_millis(random % (max.as_millis() as u64 + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_moved_bps() {
+        assert_eq!(moved_bps(1_000, 1_000), 0);
+        assert_eq!(moved_bps(1_000, 1_005), 50);
+        assert_eq!(moved_bps(1_000, 995), 50);
+        assert_eq!(moved_bps(0, 1_000), 0);
+    }
+
+    fn config() -> LiquidationConfig {
+        LiquidationConfig {
+            scan_interval: Duration::from_secs(30),
+            min_scan_interval: Duration::from_secs(10),
+            scan_jitter: Duration::ZERO,
+            block_poll_interval: Duration::from_secs(2),
+            price_move_bps: 50,
+            precheck: false,
+            precheck_margin_bps: 100,
+        }
+    }
+
+    #[test]
+    fn test_blocks_within_min_interval_trigger_one_scan() {
+        let mut schedule = ScanSchedule::new(config());
+        schedule.scanned(Some(100), HashMap::new());
+
+        // A block every 2 seconds, polled as it comes.
+        let mut triggers = Vec::new();
+        for (i, block) in (101..=105).enumerate() {
+            let elapsed = Duration::from_secs(2 * (i as u64 + 1));
+            if let Some(trigger) = schedule.next_trigger(elapsed, Some(block)) {
+                triggers.push(trigger);
+            }
+        }
+        assert_eq!(triggers, vec![ScanTrigger::NewBlock(105)]);
+    }
+
+    #[test]
+    fn test_interval_triggers_without_new_block() {
+        let mut schedule = ScanSchedule::new(config());
+        schedule.scanned(Some(100), HashMap::new());
+
+        assert_eq!(
+            schedule.next_trigger(Duration::from_secs(12), Some(100)),
+            None
+        );
+        assert_eq!(schedule.next_trigger(Duration::from_secs(20), None), None);
+        assert_eq!(
+            schedule.next_trigger(Duration::from_secs(30), Some(100)),
+            Some(ScanTrigger::Interval)
+        );
+    }
+
+    #[test]
+    fn test_jitter_is_bounded() {
+        assert_eq!(jitter(Duration::ZERO), Duration::ZERO);
+        for _ in 0..100 {
+            assert!(jitter(Duration::from_millis(10)) <= Duration::from_millis(10));
+        }
+    }
+}
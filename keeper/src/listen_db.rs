@@ -1,42 +1,188 @@
-use std::fmt::Debug;
+use std::{
+    fmt::Debug,
+    sync::atomic::{AtomicU64, AtomicU8, Ordering},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
-use log::info;
+use log::{error, info, warn};
 use serde::de::DeserializeOwned;
 use sqlx::error::Error;
-use sqlx::postgres::PgListener;
+use sqlx::postgres::{PgListener, PgNotification};
 use sqlx::Pool;
 use sqlx::Postgres;
-use tokio::task::JoinHandle;
+use tokio::{task::JoinHandle, time::sleep};
 
-// Listens to notifications from PostgreSQL channels.
+// Delay before reconnecting a lost listener, doubled after each failed attempt.
+const MIN_RECONNECT_DELAY: Duration = Duration::from_millis(500);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+const NOT_STARTED: u8 = 0;
+const CONNECTED: u8 = 1;
+const DISCONNECTED: u8 = 2;
+
+// The connection state of a listener, exposed for health checks.
+#[derive(Debug, Default)]
+pub struct ListenerHealth {
+    state: AtomicU8,
+    reconnects: AtomicU64,
+    // Unix timestamp of the last notification received, 0 before the first one.
+    last_notification_at: AtomicU64,
+}
+
+impl ListenerHealth {
+    // Whether a listener was started at all, keepers without execution mode never listen.
+    pub fn is_started(&self) -> bool {
+        self.state.load(Ordering::Relaxed) != NOT_STARTED
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.state.load(Ordering::Relaxed) == CONNECTED
+    }
+
+    // Number of times the listener lost its connection.
+    pub fn reconnects(&self) -> u64 {
+        self.reconnects.load(Ordering::Relaxed)
+    }
+
+    pub fn last_notification_at(&self) -> Option<u64> {
+        match self.last_notification_at.load(Ordering::Relaxed) {
+            0 => None,
+            timestamp => Some(timestamp),
+        }
+    }
+
+    fn set_connected(&self, connected: bool) {
+        let state = if connected { CONNECTED } else { DISCONNECTED };
+        self.state.store(state, Ordering::Relaxed);
+    }
+
+    fn notified(&self) {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default();
+        self.last_notification_at.store(now, Ordering::Relaxed);
+    }
+}
+
+// Listens to notifications from PostgreSQL channels, never returns.
+// A lost connection is reopened with a backoff and the channels are listened to again.
+// Payloads that cannot be decoded are stored in the keeper_dead_letters table.
 // @pool: A reference to a connection pool for PostgreSQL.
 // @channels: A vector of channel names to listen to.
 // @call_back: A callback function to handle the deserialized payload.
+// @on_listen: Called each time the channels are listened to, e.g. to catch up on the
+// rows inserted while the listener was down.
+// @health: Updated with the connection state of the listener.
 pub async fn start_listening<T: DeserializeOwned + Sized + Debug>(
     pool: &Pool<Postgres>,
     channels: Vec<&str>,
     call_back: impl Fn(T) -> JoinHandle<()>,
-) -> Result<(), Error> {
-    // Initiate the logger.
-    env_logger::init();
-
-    let mut listener: PgListener = PgListener::connect_with(pool)
-        .await
-        .expect("Could not connect to pool.");
-    listener.listen_all(channels).await?;
+    on_listen: impl Fn(),
+    health: &ListenerHealth,
+) {
+    let mut reconnect_delay = MIN_RECONNECT_DELAY;
     loop {
-        while let Some(notification) = listener.try_recv().await? {
-            info!(
-                "Getting notification with payload: {:?} from channel {:?}",
-                notification.payload(),
-                notification.channel()
-            );
+        let mut listener = match connect_listener(pool, &channels).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                health.set_connected(false);
+                warn!(
+                    "Could not listen to {:?}, retrying in {:?}: {}",
+                    channels, reconnect_delay, e
+                );
+                sleep(reconnect_delay).await;
+                reconnect_delay = (reconnect_delay * 2).min(MAX_RECONNECT_DELAY);
+                continue;
+            }
+        };
+        health.set_connected(true);
+        reconnect_delay = MIN_RECONNECT_DELAY;
+        info!("Listening to {:?}", channels);
+        on_listen();
 
-            let strr = notification.payload().to_owned();
-            let payload: T = serde_json::from_str::<T>(&strr).expect("Could not decode payload.");
-            info!("Payload {:?}", payload);
+        loop {
+            match listener.try_recv().await {
+                Ok(Some(notification)) => {
+                    health.notified();
+                    if let Some(payload) = decode_notification(pool, &notification).await {
+                        call_back(payload);
+                    }
+                }
+                Ok(None) => {
+                    warn!("Lost the connection of the listener, reconnecting...");
+                    break;
+                }
+                Err(e) => {
+                    warn!("Listener failed, reconnecting: {}", e);
+                    break;
+                }
+            }
+        }
+        health.set_connected(false);
+        health.reconnects.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+async fn connect_listener(pool: &Pool<Postgres>, channels: &[&str]) -> Result<PgListener, Error> {
+    let mut listener: PgListener = PgListener::connect_with(pool).await?;
+    listener.listen_all(channels.iter().copied()).await?;
+    Ok(listener)
+}
+
+// Decodes a notification payload, a payload that does not decode is stored as a dead letter.
+async fn decode_notification<T: DeserializeOwned + Sized + Debug>(
+    pool: &Pool<Postgres>,
+    notification: &PgNotification,
+) -> Option<T> {
+    info!(
+        "Getting notification with payload: {:?} from channel {:?}",
+        notification.payload(),
+        notification.channel()
+    );
 
-            call_back(payload);
+    match serde_json::from_str::<T>(notification.payload()) {
+        Ok(payload) => {
+            info!("Payload {:?}", payload);
+            Some(payload)
+        }
+        Err(e) => {
+            error!(
+                "Could not decode payload from channel {}: {}",
+                notification.channel(),
+                e
+            );
+            if let Err(e) = record_dead_letter(
+                pool,
+                notification.channel(),
+                notification.payload(),
+                &e.to_string(),
+            )
+            .await
+            {
+                error!("Could not store dead letter: {}", e);
+            }
+            None
         }
     }
 }
+
+// Stores a notification the keeper could not handle, to be inspected and replayed.
+// @pool: A reference to a connection pool for PostgreSQL.
+// @channel: The channel the notification came from.
+// @payload: The raw payload of the notification.
+// @error: Why the notification could not be handled.
+pub async fn record_dead_letter(
+    pool: &Pool<Postgres>,
+    channel: &str,
+    payload: &str,
+    error: &str,
+) -> Result<(), Error> {
+    sqlx::query("INSERT INTO keeper_dead_letters (channel, payload, error) VALUES ($1, $2, $3)")
+        .bind(channel)
+        .bind(payload)
+        .bind(error)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,158 @@
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+
+// A monotonic counter, rendered in the Prometheus text format.
+pub struct Counter {
+    name: &'static str,
+    help: &'static str,
+    value: AtomicU64,
+}
+
+impl Counter {
+    pub const fn new(name: &'static str, help: &'static str) -> Self {
+        Counter {
+            name,
+            help,
+            value: AtomicU64::new(0),
+        }
+    }
+
+    pub fn inc(&self) {
+        self.value.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> u64 {
+        self.value.load(Ordering::Relaxed)
+    }
+}
+
+// A value that can go up and down, rendered in the Prometheus text format.
+pub struct Gauge {
+    name: &'static str,
+    help: &'static str,
+    value: AtomicI64,
+}
+
+impl Gauge {
+    pub const fn new(name: &'static str, help: &'static str) -> Self {
+        Gauge {
+            name,
+            help,
+            value: AtomicI64::new(0),
+        }
+    }
+
+    pub fn set(&self, value: i64) {
+        self.value.store(value, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> i64 {
+        self.value.load(Ordering::Relaxed)
+    }
+}
+
+pub static PRICE_CACHE_HITS: Counter = Counter::new(
+    "keeper_price_cache_hits_total",
+    "Token prices served from the price cache.",
+);
+pub static PRICE_CACHE_MISSES: Counter = Counter::new(
+    "keeper_price_cache_misses_total",
+    "Token prices fetched from the price provider.",
+);
+
+pub static KEEPER_LOOP_RESTARTS: Counter = Counter::new(
+    "keeper_loop_restarts_total",
+    "Keeper loops restarted by the supervisor after stopping.",
+);
+
+pub static PREFLIGHT_REVERTS: Counter = Counter::new(
+    "keeper_preflight_reverts_total",
+    "Execute calls whose pre-flight simulation reverted.",
+);
+
+pub static UNPROFITABLE_SKIPPED: Counter = Counter::new(
+    "keeper_unprofitable_skipped_total",
+    "Actions skipped because their estimated fee exceeds their execution fee.",
+);
+pub static UNPROFITABLE_DEFERRED: Counter = Counter::new(
+    "keeper_unprofitable_deferred_total",
+    "Actions deferred because their estimated fee exceeds their execution fee.",
+);
+pub static UNPROFITABLE_SUBMITTED: Counter = Counter::new(
+    "keeper_unprofitable_submitted_total",
+    "Actions sent although their estimated fee exceeds their execution fee.",
+);
+
+pub static SUBMISSION_RETRIES: Counter = Counter::new(
+    "keeper_submission_retries_total",
+    "Execute calls scheduled again after a transient submission failure.",
+);
+pub static SUBMISSION_FAILURES: Counter = Counter::new(
+    "keeper_submission_failures_total",
+    "Execute calls given up and marked as failed.",
+);
+
+pub static KEEPER_LEADER: Gauge = Gauge::new(
+    "keeper_leader",
+    "1 while this keeper runs the liquidation and trigger loops, 0 while on standby.",
+);
+pub static KEEPER_LEADER_CHANGES: Counter = Counter::new(
+    "keeper_leader_changes_total",
+    "Times this keeper became the leader or stepped down.",
+);
+
+pub static LIQUIDATION_SCANS: Counter = Counter::new(
+    "keeper_liquidation_scans_total",
+    "Scans of the open positions for liquidations.",
+);
+pub static LIQUIDATION_SCAN_DURATION_MS: Gauge = Gauge::new(
+    "keeper_liquidation_scan_duration_ms",
+    "How long the last liquidation scan took, in milliseconds.",
+);
+
+pub static LIQUIDATION_PRECHECK_SKIPPED: Counter = Counter::new(
+    "keeper_liquidation_precheck_skipped_total",
+    "Positions the liquidation pre-check found healthy, not checked with the Reader.",
+);
+
+static COUNTERS: [&Counter; 12] = [
+    &PRICE_CACHE_HITS,
+    &PRICE_CACHE_MISSES,
+    &KEEPER_LOOP_RESTARTS,
+    &KEEPER_LEADER_CHANGES,
+    &PREFLIGHT_REVERTS,
+    &UNPROFITABLE_SKIPPED,
+    &UNPROFITABLE_DEFERRED,
+    &UNPROFITABLE_SUBMITTED,
+    &SUBMISSION_RETRIES,
+    &SUBMISSION_FAILURES,
+    &LIQUIDATION_SCANS,
+    &LIQUIDATION_PRECHECK_SKIPPED,
+];
+
+static GAUGES: [&Gauge; 2] = [&KEEPER_LEADER, &LIQUIDATION_SCAN_DURATION_MS];
+
+// Renders every keeper metric in the Prometheus text format.
+pub fn render() -> String {
+    let mut output = String::new();
+    for counter in COUNTERS {
+        output.push_str(&format!(
+            "# HELP {} {}\n# TYPE {} counter\n{} {}\n",
+            counter.name,
+            counter.help,
+            counter.name,
+            counter.name,
+            counter.get()
+        ));
+    }
+    for gauge in GAUGES {
+        output.push_str(&format!(
+            "# HELP {} {}\n# TYPE {} gauge\n{} {}\n",
+            gauge.name,
+            gauge.help,
+            gauge.name,
+            gauge.name,
+            gauge.get()
+        ));
+    }
+    output
+}
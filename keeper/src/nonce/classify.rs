@@ -0,0 +1,51 @@
+use starknet::{accounts::AccountError, core::types::StarknetError, providers::ProviderError};
+
+// Whether a submission refused by the provider may go through when sent again later.
+// Rate limits, transport failures, nonce conflicts and fee spikes are transient, while
+// e.g. a failed validation or an empty account balance will fail again.
+pub fn is_retryable_provider_error(error: &ProviderError) -> bool {
+    match error {
+        ProviderError::RateLimited => true,
+        // Errors of the transport, e.g. timeouts or a node restarting.
+        ProviderError::Other(_) => true,
+        ProviderError::StarknetError(error) => matches!(
+            error,
+            StarknetError::InvalidTransactionNonce
+                | StarknetError::InsufficientMaxFee
+                | StarknetError::FailedToReceiveTransaction
+                | StarknetError::UnexpectedError(_)
+        ),
+        _ => false,
+    }
+}
+
+// Whether a submission that failed in the account may go through when sent again later.
+// Only provider errors can be transient, signing and fee computation errors are not.
+pub fn is_retryable_account_error<S>(error: &AccountError<S>) -> bool {
+    match error {
+        AccountError::Provider(error) => is_retryable_provider_error(error),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_retryable_provider_error() {
+        assert!(is_retryable_provider_error(&ProviderError::RateLimited));
+        assert!(is_retryable_provider_error(&ProviderError::StarknetError(
+            StarknetError::InvalidTransactionNonce
+        )));
+        assert!(is_retryable_provider_error(&ProviderError::StarknetError(
+            StarknetError::InsufficientMaxFee
+        )));
+        assert!(!is_retryable_provider_error(&ProviderError::StarknetError(
+            StarknetError::InsufficientAccountBalance
+        )));
+        assert!(!is_retryable_provider_error(&ProviderError::StarknetError(
+            StarknetError::DuplicateTx
+        )));
+    }
+}
@@ -0,0 +1,21 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum NonceError {
+    #[error("Could not fetch account nonce")]
+    NonceFetchFailed(String),
+    #[error("Transaction submission failed")]
+    SubmissionFailed(String),
+    #[error("Transaction submission failed, it may be retried")]
+    RetryableSubmissionFailed(String),
+}
+
+impl NonceError {
+    // Whether sending the same calls again later may succeed.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            NonceError::NonceFetchFailed(_) | NonceError::RetryableSubmissionFailed(_)
+        )
+    }
+}
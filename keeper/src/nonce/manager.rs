@@ -0,0 +1,102 @@
+use std::sync::Arc;
+
+use log::warn;
+use starknet::{
+    accounts::{Account, Call, ConnectedAccount, SingleOwnerAccount},
+    core::types::{FieldElement, InvokeTransactionResult},
+    providers::jsonrpc::{HttpTransport, JsonRpcClient},
+    signers::LocalWallet,
+};
+use tokio::sync::Mutex;
+
+use super::{classify::is_retryable_account_error, error::NonceError};
+
+// Multiplier applied to the fee estimate to get the max fee, the starknet-rs default.
+pub const DEFAULT_FEE_MULTIPLIER: f64 = 1.1;
+
+// Wraps the keeper account to hand out nonces locally.
+// Concurrent tasks sharing the account would otherwise all read the same nonce from
+// the node and race each other, so submissions go through a single lock.
+pub struct NonceManager {
+    account: Arc<SingleOwnerAccount<JsonRpcClient<HttpTransport>, LocalWallet>>,
+    // The nonce of the next transaction, None when it must be read from chain.
+    next_nonce: Mutex<Option<FieldElement>>,
+}
+
+impl NonceManager {
+    pub fn new(
+        account: Arc<SingleOwnerAccount<JsonRpcClient<HttpTransport>, LocalWallet>>,
+    ) -> Self {
+        NonceManager {
+            account,
+            next_nonce: Mutex::new(None),
+        }
+    }
+
+    // The wrapped account, used to build calls and query the chain.
+    pub fn account(&self) -> Arc<SingleOwnerAccount<JsonRpcClient<HttpTransport>, LocalWallet>> {
+        self.account.clone()
+    }
+
+    // Sends the calls as one multicall using the next local nonce.
+    // The lock is held until the node accepted or refused the transaction, and a
+    // refusal drops the local nonce so the next submission resyncs from chain.
+    // @calls: The calls to include in the multicall.
+    pub async fn execute(&self, calls: Vec<Call>) -> Result<InvokeTransactionResult, NonceError> {
+        self.execute_with_fee_multiplier(calls, DEFAULT_FEE_MULTIPLIER)
+            .await
+    }
+
+    // Sends the calls as one multicall like execute, with a custom max fee.
+    // @calls: The calls to include in the multicall.
+    // @fee_multiplier: Applied to the fee estimate to get the max fee, raised to get
+    // through fee spikes.
+    pub async fn execute_with_fee_multiplier(
+        &self,
+        calls: Vec<Call>,
+        fee_multiplier: f64,
+    ) -> Result<InvokeTransactionResult, NonceError> {
+        let mut next_nonce = self.next_nonce.lock().await;
+        let nonce = match *next_nonce {
+            Some(nonce) => nonce,
+            None => self
+                .account
+                .get_nonce()
+                .await
+                .map_err(|e| NonceError::NonceFetchFailed(e.to_string()))?,
+        };
+
+        match self
+            .account
+            .execute(calls)
+            .nonce(nonce)
+            .fee_estimate_multiplier(fee_multiplier)
+            .send()
+            .await
+        {
+            Ok(result) => {
+                *next_nonce = Some(nonce + FieldElement::ONE);
+                Ok(result)
+            }
+            Err(e) => {
+                warn!(
+                    "Submission with nonce {} failed, resyncing from chain",
+                    nonce
+                );
+                *next_nonce = None;
+                if is_retryable_account_error(&e) {
+                    Err(NonceError::RetryableSubmissionFailed(e.to_string()))
+                } else {
+                    Err(NonceError::SubmissionFailed(e.to_string()))
+                }
+            }
+        }
+    }
+
+    // Forgets the local nonce so the next submission reads it from chain.
+    // Called by the tracker when a sent transaction is dropped, a refused submission
+    // already drops the local nonce in execute_with_fee_multiplier.
+    pub async fn resync(&self) {
+        *self.next_nonce.lock().await = None;
+    }
+}
@@ -0,0 +1,3 @@
+pub mod classify;
+pub mod error;
+pub mod manager;
@@ -0,0 +1,9 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum PreflightError {
+    #[error("Invalid pre-flight policy: {0}")]
+    ConfigError(String),
+    #[error("Database Error")]
+    DatabaseError(String),
+}
@@ -0,0 +1,5 @@
+pub mod error;
+pub mod policy;
+pub mod profitability;
+pub mod retries;
+pub mod store;
@@ -0,0 +1,191 @@
+use std::{fmt, str::FromStr};
+
+use starknet::core::{types::FieldElement, utils::parse_cairo_short_string};
+
+use super::error::PreflightError;
+
+// What the keeper does with an action whose simulation reverts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PreflightDecision {
+    // Drop the action, it is not sent.
+    Skip,
+    // Build and simulate the action again later, e.g. with fresher prices.
+    Retry,
+    // Send it anyway.
+    Submit,
+}
+
+impl PreflightDecision {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PreflightDecision::Skip => "skip",
+            PreflightDecision::Retry => "retry",
+            PreflightDecision::Submit => "submit",
+        }
+    }
+}
+
+impl FromStr for PreflightDecision {
+    type Err = PreflightError;
+
+    fn from_str(decision: &str) -> Result<Self, Self::Err> {
+        match decision {
+            "skip" => Ok(PreflightDecision::Skip),
+            "retry" => Ok(PreflightDecision::Retry),
+            "submit" => Ok(PreflightDecision::Submit),
+            other => Err(PreflightError::ConfigError(format!(
+                "{} is not skip, retry or submit",
+                other
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for PreflightDecision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+// Decides what to do with a reverted simulation from its decoded revert reason.
+// The first rule whose pattern is contained in the reason applies, the default otherwise.
+#[derive(Debug, Clone)]
+pub struct PreflightPolicy {
+    rules: Vec<(String, PreflightDecision)>,
+    default: PreflightDecision,
+}
+
+impl PreflightPolicy {
+    pub fn new(default: PreflightDecision) -> Self {
+        PreflightPolicy {
+            rules: Vec::new(),
+            default,
+        }
+    }
+
+    pub fn with_rule(mut self, pattern: impl Into<String>, decision: PreflightDecision) -> Self {
+        self.rules.push((pattern.into(), decision));
+        self
+    }
+
+    // Parses rules written as a comma separated list of reason=decision.
+    // @rules: e.g. "max_oracle_price_age_exceeded=retry,empty_order=skip".
+    // @default: The decision for the reasons matching no rule.
+    pub fn from_config_str(
+        rules: &str,
+        default: PreflightDecision,
+    ) -> Result<Self, PreflightError> {
+        let mut policy = PreflightPolicy::new(default);
+        for entry in rules.split(',').map(|entry| entry.trim()) {
+            if entry.is_empty() {
+                continue;
+            }
+            let (pattern, decision) = entry.split_once('=').ok_or_else(|| {
+                PreflightError::ConfigError(format!("Rule {} is not reason=decision", entry))
+            })?;
+            policy = policy.with_rule(pattern.trim(), decision.trim().parse()?);
+        }
+        Ok(policy)
+    }
+
+    pub fn decide(&self, reason: &str) -> PreflightDecision {
+        self.rules
+            .iter()
+            .find(|(pattern, _)| reason.contains(pattern.as_str()))
+            .map(|(_, decision)| *decision)
+            .unwrap_or(self.default)
+    }
+}
+
+// Extracts the failure reason from the revert message of a simulation.
+// Cairo short strings given as felts are decoded, e.g. 0x656d7074795f6f72646572 gives
+// empty_order. Messages in another format are reduced to their last line.
+pub fn decode_revert_reason(revert_reason: &str) -> String {
+    let reason = revert_reason
+        .rsplit_once("Failure reason:")
+        .map(|(_, reason)| reason)
+        .unwrap_or(revert_reason)
+        .trim();
+
+    // Some nodes already decode the felt, e.g. 0x... ('empty_order').
+    if let Some((_, decoded)) = reason.split_once("('") {
+        if let Some((decoded, _)) = decoded.split_once("')") {
+            return decoded.to_owned();
+        }
+    }
+
+    let felts: Option<Vec<String>> = reason
+        .trim_matches(|c| c == '(' || c == ')' || c == '.')
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|felt| !felt.is_empty())
+        .map(decode_short_string)
+        .collect();
+    match felts {
+        Some(strings) if !strings.is_empty() => strings.join(", "),
+        _ => reason
+            .lines()
+            .rev()
+            .map(str::trim)
+            .find(|line| !line.is_empty())
+            .unwrap_or(reason)
+            .to_owned(),
+    }
+}
+
+fn decode_short_string(felt: &str) -> Option<String> {
+    if !felt.starts_with("0x") {
+        return None;
+    }
+    let felt = FieldElement::from_hex_be(felt).ok()?;
+    let decoded = parse_cairo_short_string(&felt).ok()?;
+    let printable =
+        !decoded.is_empty() && decoded.chars().all(|c| c.is_ascii_graphic() || c == ' ');
+    printable.then_some(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_revert_reason() {
+        let raw = "Error in the called contract (0x1234):\nError at pc=0:37:\n\
+                   Execution failed. Failure reason: 0x656d7074795f6f72646572.\n";
+        assert_eq!(decode_revert_reason(raw), "empty_order");
+
+        let decoded = "Execution failed. Failure reason: 0x656d7074795f6f72646572 ('empty_order').";
+        assert_eq!(decode_revert_reason(decoded), "empty_order");
+
+        let multiple = "Failure reason: (0x656d7074795f6f72646572, 0x6b6579)";
+        assert_eq!(decode_revert_reason(multiple), "empty_order, key");
+
+        assert_eq!(
+            decode_revert_reason("Error in the called contract\nInsufficient max fee\n"),
+            "Insufficient max fee"
+        );
+    }
+
+    #[test]
+    fn test_policy_decide() {
+        let policy = PreflightPolicy::from_config_str(
+            "max_oracle_price_age=retry, insufficient_collateral=submit",
+            PreflightDecision::Skip,
+        )
+        .unwrap();
+
+        assert_eq!(
+            policy.decide("max_oracle_price_age_exceeded"),
+            PreflightDecision::Retry
+        );
+        assert_eq!(
+            policy.decide("insufficient_collateral"),
+            PreflightDecision::Submit
+        );
+        assert_eq!(policy.decide("empty_order"), PreflightDecision::Skip);
+
+        assert!(PreflightPolicy::from_config_str("empty_order", PreflightDecision::Skip).is_err());
+        assert!(
+            PreflightPolicy::from_config_str("empty_order=later", PreflightDecision::Skip).is_err()
+        );
+    }
+}
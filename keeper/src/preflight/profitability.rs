@@ -0,0 +1,26 @@
+// Basis points in one.
+const BPS: u128 = 10_000;
+
+// Whether an action is worth executing: its estimated fee may exceed the execution fee
+// paid by the user by at most margin_bps basis points.
+// @estimated_fee: The estimated fee of the execute call, in wei.
+// @execution_fee: The execution fee paid by the user, in wei.
+// @margin_bps: The tolerated overrun, e.g. 2000 accepts fees up to 1.2 times the execution fee.
+pub fn is_profitable(estimated_fee: u128, execution_fee: u128, margin_bps: u32) -> bool {
+    estimated_fee.saturating_mul(BPS) <= execution_fee.saturating_mul(BPS + u128::from(margin_bps))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_profitable() {
+        assert!(is_profitable(100, 100, 0));
+        assert!(!is_profitable(101, 100, 0));
+        assert!(is_profitable(120, 100, 2_000));
+        assert!(!is_profitable(121, 100, 2_000));
+        assert!(!is_profitable(1, 0, 10_000));
+        assert!(is_profitable(u128::MAX, u128::MAX, 0));
+    }
+}
@@ -0,0 +1,118 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+// The actions deferred by the pre-flight checks, whose simulation reverted with a reason
+// worth retrying or whose fee was too high.
+// Each retry is delayed a bit more than the previous one, an action is given up
+// once it used all its retries.
+#[derive(Debug)]
+pub struct PreflightRetries {
+    max_retries: u32,
+    delay: Duration,
+    actions: Mutex<HashMap<String, Retry>>,
+}
+
+#[derive(Debug)]
+struct Retry {
+    attempts: u32,
+    // None while the action is being handled again.
+    due: Option<Instant>,
+}
+
+impl PreflightRetries {
+    // @max_retries: How many times an action is simulated again.
+    // @delay: The delay before the first retry, the n-th retry waits n times longer.
+    pub fn new(max_retries: u32, delay: Duration) -> Self {
+        PreflightRetries {
+            max_retries,
+            delay,
+            actions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // Schedules another attempt of an action.
+    // Returns false, and forgets the action, once it has no retry left.
+    pub fn schedule(&self, action_key: &str) -> bool {
+        let mut actions = self.actions.lock().unwrap();
+        let attempts = actions.get(action_key).map_or(0, |retry| retry.attempts) + 1;
+        if attempts > self.max_retries {
+            actions.remove(action_key);
+            return false;
+        }
+        actions.insert(
+            action_key.to_owned(),
+            Retry {
+                attempts,
+                due: Some(Instant::now() + self.delay * attempts),
+            },
+        );
+        true
+    }
+
+    // Forgets an action that was sent or given up.
+    pub fn forget(&self, action_key: &str) {
+        self.actions.lock().unwrap().remove(action_key);
+    }
+
+    // Returns the keys of the actions due for another attempt.
+    // They are not returned again until scheduled anew.
+    pub fn take_due(&self) -> Vec<String> {
+        let now = Instant::now();
+        let mut actions = self.actions.lock().unwrap();
+        actions
+            .iter_mut()
+            .filter(|(_, retry)| retry.due.is_some_and(|due| due <= now))
+            .map(|(action_key, retry)| {
+                retry.due = None;
+                action_key.clone()
+            })
+            .collect()
+    }
+
+    // Forgets every action, due or not, and returns their keys, e.g. on shutdown.
+    pub fn take_all(&self) -> Vec<String> {
+        self.actions
+            .lock()
+            .unwrap()
+            .drain()
+            .map(|(action_key, _)| action_key)
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.actions.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retries_are_limited() {
+        let retries = PreflightRetries::new(2, Duration::ZERO);
+
+        assert!(retries.schedule("0x1"));
+        assert_eq!(retries.take_due(), vec!["0x1".to_owned()]);
+        assert!(retries.take_due().is_empty());
+
+        assert!(retries.schedule("0x1"));
+        assert!(!retries.schedule("0x1"));
+        assert!(retries.is_empty());
+
+        assert!(retries.schedule("0x2"));
+        retries.forget("0x2");
+        assert!(retries.take_due().is_empty());
+
+        assert!(retries.schedule("0x3"));
+        assert_eq!(retries.take_all(), vec!["0x3".to_owned()]);
+        assert!(retries.is_empty());
+    }
+}
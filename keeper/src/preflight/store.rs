@@ -0,0 +1,32 @@
+use sqlx::PgPool;
+
+use super::{error::PreflightError, policy::PreflightDecision};
+
+// Records a simulation that reverted before an action was sent.
+// @pool: A reference to a connection pool for PostgreSQL.
+// @action_key: The key of the order, deposit, withdrawal or position.
+// @action_type: The kind of action simulated (order, deposit, ...).
+// @revert_reason: The decoded revert reason.
+// @decision: What the keeper did with the action.
+pub async fn record_preflight_revert(
+    pool: &PgPool,
+    action_key: &str,
+    action_type: &str,
+    revert_reason: &str,
+    decision: PreflightDecision,
+) -> Result<(), PreflightError> {
+    sqlx::query(
+        "INSERT INTO keeper_preflight_reverts (action_key, action_type, revert_reason, decision)
+         VALUES ($1, $2, $3, $4)",
+    )
+    .bind(action_key)
+    .bind(action_type)
+    .bind(revert_reason)
+    .bind(decision.as_str())
+    .execute(pool)
+    .await
+    .map_err(|e| {
+        PreflightError::DatabaseError(format!("Could not record pre-flight revert: {}", e))
+    })?;
+    Ok(())
+}
@@ -0,0 +1,163 @@
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use cainome::cairo_serde::ContractAddress;
+use starknet::core::types::FieldElement;
+use tokio::sync::{Mutex, OnceCell};
+
+use crate::metrics::{PRICE_CACHE_HITS, PRICE_CACHE_MISSES};
+
+use super::{error::PriceProviderError, oracle_params::TokenOraclePrice, provider::PriceProvider};
+
+struct CacheEntry {
+    price: Arc<OnceCell<TokenOraclePrice>>,
+    created_at: Instant,
+}
+
+// Caches the prices of another provider for ttl, by token and timestamp bucket.
+// Concurrent lookups of the same price share a single request to the inner provider,
+// failed requests are not cached.
+pub struct CachedPriceProvider {
+    inner: Arc<dyn PriceProvider>,
+    ttl: Duration,
+    bucket_seconds: u64,
+    entries: Mutex<HashMap<(FieldElement, String), CacheEntry>>,
+}
+
+impl CachedPriceProvider {
+    // @inner: The provider queried on cache misses.
+    // @ttl: How long a fetched price is served from the cache.
+    // @bucket_seconds: Timestamps in the same bucket share their price.
+    pub fn new(inner: Arc<dyn PriceProvider>, ttl: Duration, bucket_seconds: u64) -> Self {
+        CachedPriceProvider {
+            inner,
+            ttl,
+            bucket_seconds: bucket_seconds.max(1),
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn bucket(&self, timestamp: &str) -> String {
+        match timestamp.parse::<u64>() {
+            Ok(timestamp) => (timestamp - timestamp % self.bucket_seconds).to_string(),
+            Err(_) => timestamp.to_owned(),
+        }
+    }
+
+    async fn entry(
+        &self,
+        token: ContractAddress,
+        timestamp: &str,
+    ) -> Arc<OnceCell<TokenOraclePrice>> {
+        let mut entries = self.entries.lock().await;
+        entries.retain(|_key, entry| entry.created_at.elapsed() < self.ttl);
+        entries
+            .entry((token.0, self.bucket(timestamp)))
+            .or_insert_with(|| CacheEntry {
+                price: Arc::new(OnceCell::new()),
+                created_at: Instant::now(),
+            })
+            .price
+            .clone()
+    }
+}
+
+#[async_trait]
+impl PriceProvider for CachedPriceProvider {
+    fn name(&self) -> String {
+        format!("cached({})", self.inner.name())
+    }
+
+    async fn get_token_price(
+        &self,
+        token: ContractAddress,
+        timestamp: &str,
+    ) -> Result<TokenOraclePrice, PriceProviderError> {
+        let cell = self.entry(token, timestamp).await;
+        let mut fetched = false;
+        let price = cell
+            .get_or_try_init(|| {
+                fetched = true;
+                self.inner.get_token_price(token, timestamp)
+            })
+            .await?;
+
+        if fetched {
+            PRICE_CACHE_MISSES.inc();
+        } else {
+            PRICE_CACHE_HITS.inc();
+        }
+        Ok(price.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    use super::*;
+
+    struct CountingPriceProvider {
+        calls: AtomicU64,
+    }
+
+    #[async_trait]
+    impl PriceProvider for CountingPriceProvider {
+        fn name(&self) -> String {
+            "counting".to_owned()
+        }
+
+        async fn get_token_price(
+            &self,
+            token: ContractAddress,
+            _timestamp: &str,
+        ) -> Result<TokenOraclePrice, PriceProviderError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            Ok(TokenOraclePrice {
+                token,
+                min_price: 3500,
+                max_price: 3500,
+                price_decimals: 0,
+                token_decimals: 18,
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cached_price_provider() {
+        let inner = Arc::new(CountingPriceProvider {
+            calls: AtomicU64::new(0),
+        });
+        let cache = Arc::new(CachedPriceProvider::new(
+            inner.clone(),
+            Duration::from_secs(60),
+            60,
+        ));
+        let eth = ContractAddress::from(FieldElement::from_hex_be("0x1").unwrap());
+
+        // Concurrent lookups in the same bucket share one request.
+        let lookups: Vec<_> = (0..10)
+            .map(|i| {
+                let cache = cache.clone();
+                tokio::spawn(async move {
+                    cache
+                        .get_token_price(eth, &(1711110600 + i).to_string())
+                        .await
+                })
+            })
+            .collect();
+        for lookup in lookups {
+            assert_eq!(lookup.await.unwrap().unwrap().max_price, 3500);
+        }
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 1);
+
+        // Another bucket is fetched again.
+        cache.get_token_price(eth, "1711110660").await.unwrap();
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 2);
+    }
+}
@@ -12,4 +12,36 @@ pub enum PragmaAPIError {
     FetchError(Box<PragmaAPIError>),
     #[error("API Key not set")]
     APIKeyNotSet(),
+    #[error("Pragma price is not a valid hex number")]
+    PriceConversion(String),
+    #[error("Could not reach the Pragma API")]
+    Request(reqwest::Error),
+}
+
+#[derive(Error, Debug)]
+pub enum PriceProviderError {
+    #[error("Pragma API error")]
+    PragmaError(#[from] PragmaAPIError),
+    #[error("Token is not in the registry")]
+    UnknownToken(String),
+    #[error("No price available for token")]
+    NoPrice(String),
+    #[error("Smart Contract Error")]
+    SmartContractError(String),
+    #[error("Every price provider failed")]
+    AllProvidersFailed(String),
+    #[error("Price provider is not configured")]
+    ConfigError(String),
+}
+
+#[derive(Error, Debug)]
+pub enum OracleParamsError {
+    #[error("No token to set prices for")]
+    NoToken(),
+    #[error("Token price set twice")]
+    DuplicateToken(String),
+    #[error("Price cannot be compacted")]
+    PriceOutOfRange(String),
+    #[error("Too many tokens for compacted price indexes")]
+    TooManyTokens(usize),
 }
@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use cainome::cairo_serde::ContractAddress;
+use starknet::core::types::FieldElement;
+
+use crate::token::registry::TokenRegistry;
+
+use super::{error::PriceProviderError, oracle_params::TokenOraclePrice, provider::PriceProvider};
+
+// Serves prices set in advance, for tests and devnets without a price feed.
+#[derive(Debug, Clone, Default)]
+pub struct FixedPriceProvider {
+    prices: HashMap<FieldElement, TokenOraclePrice>,
+}
+
+impl FixedPriceProvider {
+    pub fn new() -> Self {
+        FixedPriceProvider::default()
+    }
+
+    // Sets the price of a token.
+    // @price: The price, with price_decimals decimals.
+    // @price_decimals: The decimals of price.
+    // @token_decimals: The decimals of the token.
+    pub fn with_price(
+        mut self,
+        token: ContractAddress,
+        price: u128,
+        price_decimals: u32,
+        token_decimals: u32,
+    ) -> Self {
+        self.prices.insert(
+            token.0,
+            TokenOraclePrice {
+                token,
+                min_price: price,
+                max_price: price,
+                price_decimals,
+                token_decimals,
+            },
+        );
+        self
+    }
+
+    // Parses prices written as a comma separated list of token=price, e.g. 0x49d3...=3500.25.
+    // Token decimals are read from the token registry.
+    pub fn from_config_str(
+        config: &str,
+        token_registry: &TokenRegistry,
+    ) -> Result<Self, PriceProviderError> {
+        let mut provider = FixedPriceProvider::new();
+        for entry in config.split(',').map(|entry| entry.trim()) {
+            if entry.is_empty() {
+                continue;
+            }
+            let (token, price) = entry.split_once('=').ok_or_else(|| {
+                PriceProviderError::ConfigError(format!("Fixed price {} is not token=price", entry))
+            })?;
+            let token =
+                ContractAddress::from(FieldElement::from_hex_be(token.trim()).map_err(|e| {
+                    PriceProviderError::ConfigError(format!("fixed price token {}: {}", token, e))
+                })?);
+            let (price, price_decimals) = parse_decimal_price(price.trim())?;
+            let token_info = token_registry
+                .get(token)
+                .map_err(|e| PriceProviderError::UnknownToken(format!("{:?}", e)))?;
+            provider = provider.with_price(token, price, price_decimals, token_info.decimals);
+        }
+        Ok(provider)
+    }
+}
+
+#[async_trait]
+impl PriceProvider for FixedPriceProvider {
+    fn name(&self) -> String {
+        "fixed".to_owned()
+    }
+
+    async fn get_token_price(
+        &self,
+        token: ContractAddress,
+        _timestamp: &str,
+    ) -> Result<TokenOraclePrice, PriceProviderError> {
+        self.prices
+            .get(&token.0)
+            .cloned()
+            .ok_or_else(|| PriceProviderError::NoPrice(format!("{:#x}", token.0)))
+    }
+}
+
+// Splits a decimal price like 3500.25 into 350025 and 2 decimals.
+fn parse_decimal_price(price: &str) -> Result<(u128, u32), PriceProviderError> {
+    let (integer, fraction) = price.split_once('.').unwrap_or((price, ""));
+    let digits = format!("{}{}", integer, fraction);
+    let value = digits
+        .parse::<u128>()
+        .map_err(|e| PriceProviderError::ConfigError(format!("fixed price {}: {}", price, e)))?;
+    Ok((value, fraction.len() as u32))
+}
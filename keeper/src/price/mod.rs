@@ -1,2 +1,8 @@
+pub mod cache;
 pub mod error;
+pub mod fixed;
+pub mod onchain;
+pub mod oracle_params;
+pub mod pragma;
+pub mod provider;
 pub mod utils;
@@ -0,0 +1,80 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use cainome::cairo_serde::ContractAddress;
+use starknet::{
+    accounts::SingleOwnerAccount,
+    core::types::FieldElement,
+    providers::jsonrpc::{HttpTransport, JsonRpcClient},
+    signers::LocalWallet,
+};
+
+use crate::{token::registry::TokenRegistry, types::Oracle};
+
+use super::{error::PriceProviderError, oracle_params::TokenOraclePrice, provider::PriceProvider};
+
+// Reads the primary prices stored in the Satoru Oracle contract.
+// Oracle prices are per token unit with 30 decimals, so they have 30 - token decimals decimals.
+pub struct OnchainPriceProvider {
+    account: Arc<SingleOwnerAccount<JsonRpcClient<HttpTransport>, LocalWallet>>,
+    oracle_address: FieldElement,
+    token_registry: Arc<TokenRegistry>,
+}
+
+impl OnchainPriceProvider {
+    pub fn new(
+        account: Arc<SingleOwnerAccount<JsonRpcClient<HttpTransport>, LocalWallet>>,
+        oracle_address: FieldElement,
+        token_registry: Arc<TokenRegistry>,
+    ) -> Self {
+        OnchainPriceProvider {
+            account,
+            oracle_address,
+            token_registry,
+        }
+    }
+}
+
+#[async_trait]
+impl PriceProvider for OnchainPriceProvider {
+    fn name(&self) -> String {
+        "onchain".to_owned()
+    }
+
+    async fn get_token_price(
+        &self,
+        token: ContractAddress,
+        _timestamp: &str,
+    ) -> Result<TokenOraclePrice, PriceProviderError> {
+        let token_info = self
+            .token_registry
+            .get(token)
+            .map_err(|e| PriceProviderError::UnknownToken(format!("{:?}", e)))?;
+
+        let oracle = Oracle::new(self.oracle_address, self.account.clone());
+        let price = oracle.get_primary_price(&token).call().await.map_err(|e| {
+            PriceProviderError::SmartContractError(format!("Could not get primary price: {}", e))
+        })?;
+
+        if price.min.high != 0 || price.max.high != 0 {
+            return Err(PriceProviderError::NoPrice(format!(
+                "{:#x} primary price does not fit in u128",
+                token.0
+            )));
+        }
+        if price.max.low == 0 {
+            return Err(PriceProviderError::NoPrice(format!(
+                "{:#x} has no primary price",
+                token.0
+            )));
+        }
+
+        Ok(TokenOraclePrice {
+            token,
+            min_price: price.min.low,
+            max_price: price.max.low,
+            price_decimals: 30u32.saturating_sub(token_info.decimals),
+            token_decimals: token_info.decimals,
+        })
+    }
+}
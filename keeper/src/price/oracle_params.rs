@@ -0,0 +1,349 @@
+use std::collections::HashSet;
+
+use cainome::cairo_serde::{ContractAddress, U256};
+use starknet::core::types::FieldElement;
+
+use super::error::OracleParamsError;
+
+// Bit lengths of the values packed in the compacted Satoru oracle params.
+const COMPACTED_PRICE_BIT_LENGTH: u32 = 32;
+const COMPACTED_PRICE_INDEX_BIT_LENGTH: u32 = 8;
+const COMPACTED_DECIMAL_BIT_LENGTH: u32 = 8;
+// Prices are stored per unit of token with 30 decimals, as in GMX.
+pub const FLOAT_PRECISION_DECIMALS: i32 = 30;
+// The keeper signs as the single oracle signer with index 0.
+const SIGNER_INFO: u128 = 1;
+
+// A fetched price for one token.
+// @token: The token priced.
+// @min_price: The minimum price, as returned by the price source.
+// @max_price: The maximum price, as returned by the price source.
+// @price_decimals: The decimals of min_price and max_price.
+// @token_decimals: The decimals of the token.
+#[derive(Debug, Clone)]
+pub struct TokenOraclePrice {
+    pub token: ContractAddress,
+    pub min_price: u128,
+    pub max_price: u128,
+    pub price_decimals: u32,
+    pub token_decimals: u32,
+}
+
+impl TokenOraclePrice {
+    // The middle of min_price and max_price.
+    pub fn mid_price(&self) -> u128 {
+        self.min_price / 2 + self.max_price / 2
+    }
+}
+
+// The oracle params expected by Satoru handlers.
+// Every abigen generated SetPricesParams can be built from it with impl_from_oracle_params!.
+#[derive(Debug, Clone)]
+pub struct OracleParams {
+    pub signer_info: U256,
+    pub tokens: Vec<ContractAddress>,
+    pub compacted_min_oracle_block_numbers: Vec<u64>,
+    pub compacted_max_oracle_block_numbers: Vec<u64>,
+    pub compacted_oracle_timestamps: Vec<u64>,
+    pub compacted_decimals: Vec<U256>,
+    pub compacted_min_prices: Vec<U256>,
+    pub compacted_min_prices_indexes: Vec<U256>,
+    pub compacted_max_prices: Vec<U256>,
+    pub compacted_max_prices_indexes: Vec<U256>,
+    pub signatures: Vec<Vec<FieldElement>>,
+    pub price_feed_tokens: Vec<ContractAddress>,
+}
+
+// Implements From<OracleParams> for the SetPricesParams generated in the calling module.
+#[macro_export]
+macro_rules! impl_from_oracle_params {
+    ($set_prices_params:ident) => {
+        impl From<$crate::price::oracle_params::OracleParams> for $set_prices_params {
+            fn from(params: $crate::price::oracle_params::OracleParams) -> Self {
+                $set_prices_params {
+                    signer_info: params.signer_info,
+                    tokens: params.tokens,
+                    compacted_min_oracle_block_numbers: params.compacted_min_oracle_block_numbers,
+                    compacted_max_oracle_block_numbers: params.compacted_max_oracle_block_numbers,
+                    compacted_oracle_timestamps: params.compacted_oracle_timestamps,
+                    compacted_decimals: params.compacted_decimals,
+                    compacted_min_prices: params.compacted_min_prices,
+                    compacted_min_prices_indexes: params.compacted_min_prices_indexes,
+                    compacted_max_prices: params.compacted_max_prices,
+                    compacted_max_prices_indexes: params.compacted_max_prices_indexes,
+                    signatures: params.signatures,
+                    price_feed_tokens: params.price_feed_tokens,
+                }
+            }
+        }
+    };
+}
+
+// Builds the oracle params setting the price of every given token.
+// @prices: The fetched price of every token, without duplicates.
+// @block_number: The block the prices are reported for.
+// @timestamp: The timestamp of that block.
+pub fn build_oracle_params(
+    prices: &[TokenOraclePrice],
+    block_number: u64,
+    timestamp: u64,
+) -> Result<OracleParams, OracleParamsError> {
+    if prices.is_empty() {
+        return Err(OracleParamsError::NoToken());
+    }
+    if prices.len() > (1 << COMPACTED_PRICE_INDEX_BIT_LENGTH) {
+        return Err(OracleParamsError::TooManyTokens(prices.len()));
+    }
+
+    let mut seen_tokens = HashSet::new();
+    let mut decimals = Vec::with_capacity(prices.len());
+    let mut min_prices = Vec::with_capacity(prices.len());
+    let mut max_prices = Vec::with_capacity(prices.len());
+    for price in prices {
+        if !seen_tokens.insert(price.token.0) {
+            return Err(OracleParamsError::DuplicateToken(format!(
+                "{:#x}",
+                price.token.0
+            )));
+        }
+        let exponent =
+            FLOAT_PRECISION_DECIMALS - price.token_decimals as i32 - price.price_decimals as i32;
+        let (min_price, max_price, price_decimals) =
+            compact_price(price.min_price, price.max_price, exponent).map_err(|e| match e {
+                OracleParamsError::PriceOutOfRange(reason) => {
+                    OracleParamsError::PriceOutOfRange(format!("{:#x}: {}", price.token.0, reason))
+                }
+                other => other,
+            })?;
+        decimals.push(price_decimals);
+        min_prices.push(min_price);
+        max_prices.push(max_price);
+    }
+
+    // A single signer reports one price per token, so token i uses price index i.
+    let price_indexes: Vec<u128> = (0..prices.len() as u128).collect();
+
+    Ok(OracleParams {
+        signer_info: U256 {
+            low: SIGNER_INFO,
+            high: 0,
+        },
+        tokens: prices.iter().map(|price| price.token).collect(),
+        compacted_min_oracle_block_numbers: vec![block_number; prices.len()],
+        compacted_max_oracle_block_numbers: vec![block_number; prices.len()],
+        compacted_oracle_timestamps: vec![timestamp; prices.len()],
+        compacted_decimals: compact_values(&decimals, COMPACTED_DECIMAL_BIT_LENGTH),
+        compacted_min_prices: compact_values(&min_prices, COMPACTED_PRICE_BIT_LENGTH),
+        compacted_min_prices_indexes: compact_values(
+            &price_indexes,
+            COMPACTED_PRICE_INDEX_BIT_LENGTH,
+        ),
+        compacted_max_prices: compact_values(&max_prices, COMPACTED_PRICE_BIT_LENGTH),
+        compacted_max_prices_indexes: compact_values(
+            &price_indexes,
+            COMPACTED_PRICE_INDEX_BIT_LENGTH,
+        ),
+        // Satoru does not verify oracle signatures yet, an empty (r, s) is sent per token.
+        signatures: vec![vec![FieldElement::ZERO, FieldElement::ZERO]; prices.len()],
+        price_feed_tokens: vec![],
+    })
+}
+
+// Reduces a min/max price pair to values fitting the compacted price bit length.
+// Returns the compacted min and max prices and the decimals they share, such that
+// price * 10^decimals is the price per unit of token with 30 decimals.
+// The min price is rounded down and the max price rounded up.
+// @exponent: The power of ten turning the raw prices into 30 decimals per unit.
+fn compact_price(
+    min_price: u128,
+    max_price: u128,
+    exponent: i32,
+) -> Result<(u128, u128, u128), OracleParamsError> {
+    if min_price > max_price {
+        return Err(OracleParamsError::PriceOutOfRange(format!(
+            "min price {} above max price {}",
+            min_price, max_price
+        )));
+    }
+    let max_compacted_price = (1u128 << COMPACTED_PRICE_BIT_LENGTH) - 1;
+    let max_decimals = (1i32 << COMPACTED_DECIMAL_BIT_LENGTH) - 1;
+
+    let mut shift: u32 = if exponent < 0 {
+        exponent.unsigned_abs()
+    } else {
+        0
+    };
+    loop {
+        let divisor = 10u128.checked_pow(shift).ok_or_else(|| {
+            OracleParamsError::PriceOutOfRange(format!("cannot compact price {}", max_price))
+        })?;
+        let compacted_max_price = max_price.div_ceil(divisor);
+        if compacted_max_price <= max_compacted_price {
+            let decimals = exponent + shift as i32;
+            if decimals > max_decimals {
+                return Err(OracleParamsError::PriceOutOfRange(format!(
+                    "{} decimals do not fit",
+                    decimals
+                )));
+            }
+            return Ok((min_price / divisor, compacted_max_price, decimals as u128));
+        }
+        shift += 1;
+    }
+}
+
+// Packs values of bit_length bits into u256 slots, least significant bits first.
+// This is the layout read back by get_uncompacted_value in Satoru's oracle_utils.
+fn compact_values(values: &[u128], bit_length: u32) -> Vec<U256> {
+    let values_per_slot = (256 / bit_length) as usize;
+    values
+        .chunks(values_per_slot)
+        .map(|chunk| {
+            let mut slot = U256 { low: 0, high: 0 };
+            for (i, value) in chunk.iter().enumerate() {
+                let offset = i as u32 * bit_length;
+                if offset < 128 {
+                    slot.low |= value << offset;
+                } else {
+                    slot.high |= value << (offset - 128);
+                }
+            }
+            slot
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(address: &str) -> ContractAddress {
+        ContractAddress::from(FieldElement::from_hex_be(address).unwrap())
+    }
+
+    #[test]
+    fn test_compact_prices() {
+        let compacted = compact_values(&[10000, 500000], COMPACTED_PRICE_BIT_LENGTH);
+        assert_eq!(
+            compacted,
+            vec![U256 {
+                low: 2147483648010000,
+                high: 0
+            }]
+        );
+    }
+
+    #[test]
+    fn test_compact_values_across_slots() {
+        let values: Vec<u128> = (1..=40).collect();
+        let compacted = compact_values(&values, COMPACTED_DECIMAL_BIT_LENGTH);
+        assert_eq!(compacted.len(), 2);
+        assert_eq!(compacted[0].low & 0xff, 1);
+        assert_eq!(compacted[0].low >> 120, 16);
+        assert_eq!(compacted[0].high & 0xff, 17);
+        assert_eq!(compacted[0].high >> 120, 32);
+        assert_eq!(compacted[1].low & 0xff, 33);
+        assert_eq!(compacted[1].high, 0);
+    }
+
+    #[test]
+    fn test_compact_price_too_large() {
+        // ETH/USD from Pragma with 8 decimals, ETH has 18 decimals.
+        let (min, max, decimals) = compact_price(341634863246, 341634863246, 4).unwrap();
+        assert_eq!(min, 3416348632);
+        assert_eq!(max, 3416348633);
+        assert_eq!(decimals, 6);
+    }
+
+    #[test]
+    fn test_compact_price_negative_exponent() {
+        let (min, max, decimals) = compact_price(123456789, 123456789, -2).unwrap();
+        assert_eq!(min, 1234567);
+        assert_eq!(max, 1234568);
+        assert_eq!(decimals, 0);
+    }
+
+    #[test]
+    fn test_compact_price_min_above_max() {
+        assert!(compact_price(2, 1, 0).is_err());
+    }
+
+    #[test]
+    fn test_build_oracle_params() {
+        let prices = vec![
+            TokenOraclePrice {
+                token: token("0x1"),
+                min_price: 341634863246,
+                max_price: 341634863246,
+                price_decimals: 8,
+                token_decimals: 18,
+            },
+            TokenOraclePrice {
+                token: token("0x2"),
+                min_price: 100000000,
+                max_price: 100010000,
+                price_decimals: 8,
+                token_decimals: 6,
+            },
+        ];
+
+        let params = build_oracle_params(&prices, 64901, 1711110660).unwrap();
+
+        assert_eq!(params.signer_info, U256 { low: 1, high: 0 });
+        assert_eq!(params.tokens, vec![token("0x1"), token("0x2")]);
+        assert_eq!(
+            params.compacted_min_oracle_block_numbers,
+            vec![64901, 64901]
+        );
+        assert_eq!(
+            params.compacted_max_oracle_block_numbers,
+            vec![64901, 64901]
+        );
+        assert_eq!(
+            params.compacted_oracle_timestamps,
+            vec![1711110660, 1711110660]
+        );
+        assert_eq!(
+            params.compacted_decimals,
+            vec![U256 {
+                low: 6 | 16 << 8,
+                high: 0
+            }]
+        );
+        assert_eq!(
+            params.compacted_min_prices,
+            vec![U256 {
+                low: 3416348632 | 100000000 << 32,
+                high: 0
+            }]
+        );
+        assert_eq!(
+            params.compacted_max_prices,
+            vec![U256 {
+                low: 3416348633 | 100010000 << 32,
+                high: 0
+            }]
+        );
+        assert_eq!(
+            params.compacted_min_prices_indexes,
+            vec![U256 {
+                low: 1 << 8,
+                high: 0
+            }]
+        );
+        assert_eq!(params.signatures.len(), 2);
+    }
+
+    #[test]
+    fn test_build_oracle_params_duplicate_token() {
+        let price = TokenOraclePrice {
+            token: token("0x1"),
+            min_price: 1,
+            max_price: 1,
+            price_decimals: 8,
+            token_decimals: 18,
+        };
+        let result = build_oracle_params(&[price.clone(), price], 1, 1);
+        assert!(matches!(result, Err(OracleParamsError::DuplicateToken(_))));
+    }
+}
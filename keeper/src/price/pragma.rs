@@ -0,0 +1,85 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use cainome::cairo_serde::ContractAddress;
+
+use crate::token::registry::TokenRegistry;
+
+use super::{
+    error::{PragmaAPIError, PriceProviderError},
+    oracle_params::TokenOraclePrice,
+    provider::PriceProvider,
+    utils::{get_pragma_price, pragma_api_key, PathParams, QueryParams, PRAGMA_API_URL},
+};
+
+// Prices tokens with the Pragma API, using the pair of the token in the registry.
+// @api_url: The Pragma API.
+// @api_key: The Pragma API key, read from PRAGMA_API_KEY on each request when None.
+pub struct PragmaPriceProvider {
+    token_registry: Arc<TokenRegistry>,
+    api_url: String,
+    api_key: Option<String>,
+}
+
+impl PragmaPriceProvider {
+    pub fn new(token_registry: Arc<TokenRegistry>) -> Self {
+        PragmaPriceProvider {
+            token_registry,
+            api_url: PRAGMA_API_URL.to_owned(),
+            api_key: None,
+        }
+    }
+
+    // Uses another Pragma API with its own key.
+    pub fn with_api(mut self, api_url: &str, api_key: String) -> Self {
+        self.api_url = api_url.to_owned();
+        self.api_key = Some(api_key);
+        self
+    }
+}
+
+#[async_trait]
+impl PriceProvider for PragmaPriceProvider {
+    fn name(&self) -> String {
+        "pragma".to_owned()
+    }
+
+    async fn get_token_price(
+        &self,
+        token: ContractAddress,
+        timestamp: &str,
+    ) -> Result<TokenOraclePrice, PriceProviderError> {
+        let token_info = self
+            .token_registry
+            .get(token)
+            .map_err(|e| PriceProviderError::UnknownToken(format!("{:?}", e)))?;
+        let (base, quote) = token_info.pair();
+        let path = PathParams {
+            base,
+            quote,
+            timestamp: timestamp.to_owned(),
+            interval: "1min".to_owned(),
+        };
+
+        let query = QueryParams {
+            routing: false,
+            aggregation: "median".to_owned(),
+        };
+
+        let api_key = match &self.api_key {
+            Some(api_key) => api_key.clone(),
+            None => pragma_api_key()?,
+        };
+        let price_info = get_pragma_price(&self.api_url, &api_key, path, query).await?;
+        let price = u128::from_str_radix(price_info.price.as_str().trim_start_matches("0x"), 16)
+            .map_err(|e| PragmaAPIError::PriceConversion(format!("{}: {}", price_info.price, e)))?;
+
+        Ok(TokenOraclePrice {
+            token,
+            min_price: price,
+            max_price: price,
+            price_decimals: price_info.decimals as u32,
+            token_decimals: token_info.decimals,
+        })
+    }
+}
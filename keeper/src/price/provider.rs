@@ -0,0 +1,196 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use cainome::cairo_serde::ContractAddress;
+use log::warn;
+use starknet::{
+    accounts::SingleOwnerAccount,
+    core::types::FieldElement,
+    providers::jsonrpc::{HttpTransport, JsonRpcClient},
+    signers::LocalWallet,
+};
+
+use crate::{config::PriceConfig, token::registry::TokenRegistry};
+
+use super::{
+    cache::CachedPriceProvider, error::PriceProviderError, fixed::FixedPriceProvider,
+    onchain::OnchainPriceProvider, oracle_params::TokenOraclePrice, pragma::PragmaPriceProvider,
+};
+
+// A source of token prices.
+#[async_trait]
+pub trait PriceProvider: Send + Sync {
+    // The provider name, used in logs.
+    fn name(&self) -> String;
+
+    // Returns the price of a token.
+    // @token: The token to price.
+    // @timestamp: The unix timestamp to price the token at, ignored by live sources.
+    async fn get_token_price(
+        &self,
+        token: ContractAddress,
+        timestamp: &str,
+    ) -> Result<TokenOraclePrice, PriceProviderError>;
+}
+
+// Asks each provider in turn and returns the first price found.
+pub struct FallbackPriceProvider {
+    providers: Vec<Arc<dyn PriceProvider>>,
+}
+
+impl FallbackPriceProvider {
+    pub fn new(providers: Vec<Arc<dyn PriceProvider>>) -> Self {
+        FallbackPriceProvider { providers }
+    }
+}
+
+#[async_trait]
+impl PriceProvider for FallbackPriceProvider {
+    fn name(&self) -> String {
+        self.providers
+            .iter()
+            .map(|provider| provider.name())
+            .collect::<Vec<String>>()
+            .join(",")
+    }
+
+    async fn get_token_price(
+        &self,
+        token: ContractAddress,
+        timestamp: &str,
+    ) -> Result<TokenOraclePrice, PriceProviderError> {
+        let mut errors: Vec<String> = Vec::new();
+        for provider in &self.providers {
+            match provider.get_token_price(token, timestamp).await {
+                Ok(price) => return Ok(price),
+                Err(e) => {
+                    warn!(
+                        "Price provider {} failed for token {:#x}: {:?}",
+                        provider.name(),
+                        token.0,
+                        e
+                    );
+                    errors.push(format!("{}: {:?}", provider.name(), e));
+                }
+            }
+        }
+        Err(PriceProviderError::AllProvidersFailed(errors.join(", ")))
+    }
+}
+
+// Builds the configured price providers, several providers are used as a fallback
+// chain in order. The chain is put behind a price cache unless the cache ttl is zero.
+// @account: The keeper account, used by the onchain provider to read the Oracle.
+// @oracle_address: The address of the Oracle read by the onchain provider.
+// @config: The price section of the keeper config.
+// @token_registry: The tokens the providers can price.
+pub fn price_provider_from_config(
+    account: Arc<SingleOwnerAccount<JsonRpcClient<HttpTransport>, LocalWallet>>,
+    oracle_address: FieldElement,
+    config: &PriceConfig,
+    token_registry: Arc<TokenRegistry>,
+) -> Result<Arc<dyn PriceProvider>, PriceProviderError> {
+    let mut providers: Vec<Arc<dyn PriceProvider>> = Vec::new();
+    for name in &config.providers {
+        let provider: Arc<dyn PriceProvider> = match name.as_str() {
+            "pragma" => Arc::new(PragmaPriceProvider::new(Arc::clone(&token_registry))),
+            "onchain" => Arc::new(OnchainPriceProvider::new(
+                account.clone(),
+                oracle_address,
+                Arc::clone(&token_registry),
+            )),
+            "fixed" => {
+                let fixed_prices = config.fixed_prices.as_deref().ok_or_else(|| {
+                    PriceProviderError::ConfigError("fixed prices not set".to_owned())
+                })?;
+                Arc::new(FixedPriceProvider::from_config_str(
+                    fixed_prices,
+                    &token_registry,
+                )?)
+            }
+            other => {
+                return Err(PriceProviderError::ConfigError(format!(
+                    "Unknown price provider: {}",
+                    other
+                )))
+            }
+        };
+        providers.push(provider);
+    }
+
+    let provider: Arc<dyn PriceProvider> = match providers.len() {
+        0 => {
+            return Err(PriceProviderError::ConfigError(
+                "No price provider set".to_owned(),
+            ))
+        }
+        1 => providers.remove(0),
+        _ => Arc::new(FallbackPriceProvider::new(providers)),
+    };
+
+    if config.cache_ttl.is_zero() {
+        return Ok(provider);
+    }
+    Ok(Arc::new(CachedPriceProvider::new(
+        provider,
+        config.cache_ttl,
+        config.cache_bucket_seconds,
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::price::error::PragmaAPIError;
+
+    #[tokio::test]
+    async fn test_fallback_price_provider() {
+        let eth = ContractAddress::from(FieldElement::from_hex_be("0x1").unwrap());
+        let usdc = ContractAddress::from(FieldElement::from_hex_be("0x2").unwrap());
+        let providers: Vec<Arc<dyn PriceProvider>> = vec![
+            Arc::new(FixedPriceProvider::new().with_price(eth, 3500, 0, 18)),
+            Arc::new(
+                FixedPriceProvider::new()
+                    .with_price(eth, 3400, 0, 18)
+                    .with_price(usdc, 1, 0, 6),
+            ),
+        ];
+        let provider = FallbackPriceProvider::new(providers);
+
+        let eth_price = provider.get_token_price(eth, "0").await.unwrap();
+        assert_eq!(eth_price.max_price, 3500);
+        let usdc_price = provider.get_token_price(usdc, "0").await.unwrap();
+        assert_eq!(usdc_price.max_price, 1);
+
+        let unknown = ContractAddress::from(FieldElement::from_hex_be("0x3").unwrap());
+        assert!(matches!(
+            provider.get_token_price(unknown, "0").await,
+            Err(PriceProviderError::AllProvidersFailed(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_fallback_on_unreachable_pragma() {
+        let token_registry = Arc::new(TokenRegistry::load("./resources/tokens.toml").unwrap());
+        let eth = ContractAddress::from(
+            FieldElement::from_hex_be(
+                "0x049d36570d4e46f48e99674bd3fcc84644ddd6b96f7c741b1562b82f9e004dc7",
+            )
+            .unwrap(),
+        );
+        // Nothing listens on port 1.
+        let pragma = PragmaPriceProvider::new(token_registry)
+            .with_api("http://127.0.0.1:1", "key".to_owned());
+        assert!(matches!(
+            pragma.get_token_price(eth, "0").await,
+            Err(PriceProviderError::PragmaError(PragmaAPIError::Request(_)))
+        ));
+
+        let provider = FallbackPriceProvider::new(vec![
+            Arc::new(pragma),
+            Arc::new(FixedPriceProvider::new().with_price(eth, 3500, 0, 18)),
+        ]);
+        let eth_price = provider.get_token_price(eth, "0").await.unwrap();
+        assert_eq!(eth_price.max_price, 3500);
+    }
+}
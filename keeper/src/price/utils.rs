@@ -1,16 +1,22 @@
-use cainome::cairo_serde::U256;
+use cainome::cairo_serde::{ContractAddress, U256};
 use dotenv::dotenv;
+use ethnum::U256 as EthU256;
 use reqwest;
 use serde::Deserialize;
+use starknet::core::types::FieldElement;
 use std::env;
 
-use super::error::PragmaAPIError;
-
-use crate::{
-    trade::utils::get_token_name_from_address,
-    types::{Market, MarketPrices, PriceReader},
+use super::{
+    error::{PragmaAPIError, PriceProviderError},
+    oracle_params::{TokenOraclePrice, FLOAT_PRECISION_DECIMALS},
+    provider::PriceProvider,
 };
 
+use crate::types::{Market, MarketPrices, PriceReader};
+
+// The Pragma API serving the prices of the pragma price provider.
+pub const PRAGMA_API_URL: &str = "https://api.dev.pragma.build/node/v1/data";
+
 #[derive(Deserialize, Clone)]
 pub struct PathParams {
     pub base: String,
@@ -36,123 +42,160 @@ pub struct PriceInfo {
 }
 
 // Get price from pragma api for a specific token
+// @api_url: The Pragma API, PRAGMA_API_URL unless testing.
+// @api_key: The Pragma API key, see pragma_api_key.
 pub async fn get_pragma_price(
+    api_url: &str,
+    api_key: &str,
     path: PathParams,
     query: QueryParams,
 ) -> Result<PriceInfo, PragmaAPIError> {
     let api_url = format!(
-        "https://api.dev.pragma.build/node/v1/data/{}/{}?interval={}&aggregation={}&timestamp={}",
-        path.base, path.quote, path.interval, query.aggregation, path.timestamp
+        "{}/{}/{}?interval={}&aggregation={}&timestamp={}",
+        api_url, path.base, path.quote, path.interval, query.aggregation, path.timestamp
     );
-    match fetch_data(&api_url).await {
-        Ok(price_info) => Ok(price_info),
-        Err(err) => Err(err),
+    fetch_data(&api_url, api_key).await
+}
+
+// The Pragma API key, read from PRAGMA_API_KEY.
+pub fn pragma_api_key() -> Result<String, PragmaAPIError> {
+    dotenv().ok();
+
+    let api_key = env::var("PRAGMA_API_KEY").map_err(|_e| PragmaAPIError::APIKeyNotSet())?;
+    if api_key.is_empty() {
+        return Err(PragmaAPIError::APIKeyNotSet());
     }
+    Ok(api_key)
+}
+
+// The fetched prices of the tokens of a market.
+// @index_token_price: None for swap only markets, which have no index token.
+#[derive(Debug, Clone)]
+pub struct MarketTokenPrices {
+    pub long_token_price: TokenOraclePrice,
+    pub short_token_price: TokenOraclePrice,
+    pub index_token_price: Option<TokenOraclePrice>,
+}
+
+// Fetch the long, short and index token prices of a Market
+pub async fn get_market_token_prices(
+    price_provider: &dyn PriceProvider,
+    market: &Market,
+    timestamp: String,
+) -> Result<MarketTokenPrices, PriceProviderError> {
+    let long_token_price = price_provider
+        .get_token_price(market.long_token, &timestamp)
+        .await?;
+    let short_token_price = price_provider
+        .get_token_price(market.short_token, &timestamp)
+        .await?;
+    let index_token_price = if market.index_token == ContractAddress::from(FieldElement::ZERO) {
+        None
+    } else {
+        Some(
+            price_provider
+                .get_token_price(market.index_token, &timestamp)
+                .await?,
+        )
+    };
+
+    Ok(MarketTokenPrices {
+        long_token_price,
+        short_token_price,
+        index_token_price,
+    })
 }
 
 // Fetch prices to build MarketPrices from Market
 pub async fn get_market_prices(
+    price_provider: &dyn PriceProvider,
     market: Market,
     timestamp: String,
-) -> Result<MarketPrices, PragmaAPIError> {
-    let mut path = PathParams {
-        base: get_token_name_from_address(market.long_token).to_owned(),
-        quote: "usd".to_owned(),
-        timestamp,
-        interval: "1min".to_owned(),
-    };
+) -> Result<MarketPrices, PriceProviderError> {
+    let prices = get_market_token_prices(price_provider, &market, timestamp).await?;
+    Ok(to_raw_market_prices(&prices))
+}
 
-    let query = QueryParams {
-        routing: false,
-        aggregation: "median".to_owned(),
-    };
-    let long_token_price_info = get_pragma_price(path.clone(), query.clone())
-        .await
-        .expect("get_pragma_price failed on long_token");
+// Fetch prices to build MarketPrices from Market, per unit of token with 30 decimals.
+// The liquidation pre-check and the Reader confirmation both use these prices.
+pub async fn get_float_market_prices(
+    price_provider: &dyn PriceProvider,
+    market: &Market,
+    timestamp: String,
+) -> Result<MarketPrices, PriceProviderError> {
+    let prices = get_market_token_prices(price_provider, market, timestamp).await?;
+    Ok(to_float_market_prices(&prices))
+}
 
-    path.base = get_token_name_from_address(market.short_token);
-    let short_token_price_info = get_pragma_price(path.clone(), query.clone())
-        .await
-        .expect("get_pragma_price failed on short_token");
+// The prices as returned by the price source.
+pub fn to_raw_market_prices(prices: &MarketTokenPrices) -> MarketPrices {
+    to_market_prices(prices, to_price_reader)
+}
 
-    path.base = get_token_name_from_address(market.index_token);
-    let index_token_price_info = get_pragma_price(path, query)
-        .await
-        .expect("get_pragma_price failed on index_token");
-
-    let index_token_price = u128::from_str_radix(
-        index_token_price_info
-            .price
-            .as_str()
-            .trim_start_matches("0x"),
-        16,
-    )
-    .expect("Could not convert index token hex price to uint");
-    let long_token_price = u128::from_str_radix(
-        long_token_price_info
-            .price
-            .as_str()
-            .trim_start_matches("0x"),
-        16,
-    )
-    .expect("Could not convert long token hex price to uint");
-    let short_token_price = u128::from_str_radix(
-        short_token_price_info
-            .price
-            .as_str()
-            .trim_start_matches("0x"),
-        16,
-    )
-    .expect("Could not convert short token hex price to uint");
-    Ok(MarketPrices {
-        index_token_price: PriceReader {
-            min: U256 {
-                low: index_token_price,
-                high: 0,
-            },
-            max: U256 {
-                low: index_token_price,
-                high: 0,
-            },
-        },
-        long_token_price: PriceReader {
-            min: U256 {
-                low: long_token_price,
-                high: 0,
-            },
-            max: U256 {
-                low: long_token_price,
-                high: 0,
+// The prices per unit of token with 30 decimals, as the contracts and the liquidation
+// pre-check compute usd values.
+pub fn to_float_market_prices(prices: &MarketTokenPrices) -> MarketPrices {
+    to_market_prices(prices, to_float_price_reader)
+}
+
+fn to_market_prices(
+    prices: &MarketTokenPrices,
+    to_reader: fn(&TokenOraclePrice) -> PriceReader,
+) -> MarketPrices {
+    MarketPrices {
+        index_token_price: prices.index_token_price.as_ref().map(to_reader).unwrap_or(
+            PriceReader {
+                min: U256 { low: 0, high: 0 },
+                max: U256 { low: 0, high: 0 },
             },
+        ),
+        long_token_price: to_reader(&prices.long_token_price),
+        short_token_price: to_reader(&prices.short_token_price),
+    }
+}
+
+fn to_price_reader(price: &TokenOraclePrice) -> PriceReader {
+    PriceReader {
+        min: U256 {
+            low: price.min_price,
+            high: 0,
         },
-        short_token_price: PriceReader {
-            min: U256 {
-                low: short_token_price,
-                high: 0,
-            },
-            max: U256 {
-                low: short_token_price,
-                high: 0,
-            },
+        max: U256 {
+            low: price.max_price,
+            high: 0,
         },
-    })
+    }
 }
 
-async fn fetch_data(url: &str) -> Result<PriceInfo, PragmaAPIError> {
-    dotenv().ok();
-
-    let client = reqwest::Client::new();
-    let api_key = env::var("PRAGMA_API_KEY").map_err(|_e| PragmaAPIError::APIKeyNotSet())?;
-    if api_key.is_empty() {
-        return Err(PragmaAPIError::APIKeyNotSet());
+fn to_float_price_reader(price: &TokenOraclePrice) -> PriceReader {
+    let exponent =
+        FLOAT_PRECISION_DECIMALS - price.token_decimals as i32 - price.price_decimals as i32;
+    PriceReader {
+        min: to_float_price(price.min_price, exponent),
+        max: to_float_price(price.max_price, exponent),
     }
+}
+
+// @exponent: The power of ten turning the raw price into 30 decimals per unit.
+fn to_float_price(price: u128, exponent: i32) -> U256 {
+    let scale = EthU256::from(10u8).pow(exponent.unsigned_abs());
+    let price = if exponent < 0 {
+        EthU256::from(price) / scale
+    } else {
+        EthU256::from(price).saturating_mul(scale)
+    };
+    let (high, low) = price.into_words();
+    U256 { low, high }
+}
 
+async fn fetch_data(url: &str, api_key: &str) -> Result<PriceInfo, PragmaAPIError> {
+    let client = reqwest::Client::new();
     let response = client
         .get(url)
         .header("x-api-key", api_key)
         .send()
         .await
-        .unwrap();
+        .map_err(PragmaAPIError::Request)?;
     match response.status() {
         reqwest::StatusCode::OK => match response.json::<PriceInfo>().await {
             Ok(parsed) => Ok(parsed),
@@ -166,6 +209,104 @@ async fn fetch_data(url: &str) -> Result<PriceInfo, PragmaAPIError> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::price::fixed::FixedPriceProvider;
+
+    fn token_price(price: u128, price_decimals: u32, token_decimals: u32) -> TokenOraclePrice {
+        TokenOraclePrice {
+            token: ContractAddress::from(FieldElement::ONE),
+            min_price: price,
+            max_price: price,
+            price_decimals,
+            token_decimals,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_float_market_prices() {
+        let eth = ContractAddress::from(FieldElement::TWO);
+        let usdc = ContractAddress::from(FieldElement::THREE);
+        let provider = FixedPriceProvider::new()
+            .with_price(eth, 2_500_00000000, 8, 18)
+            .with_price(usdc, 1_00000000, 8, 6);
+        let market = Market {
+            market_token: ContractAddress::from(FieldElement::ONE),
+            index_token: eth,
+            long_token: eth,
+            short_token: usdc,
+        };
+
+        // The liquidation scan and check-position pass these prices to both the pre-check
+        // and the Reader, they match the prices the pre-check is tested with.
+        let prices = get_float_market_prices(&provider, &market, "0".to_owned())
+            .await
+            .unwrap();
+        let token_prices = get_market_token_prices(&provider, &market, "0".to_owned())
+            .await
+            .unwrap();
+        let expected = to_float_market_prices(&token_prices);
+        for (price, expected) in [
+            (prices.index_token_price, expected.index_token_price),
+            (prices.long_token_price, expected.long_token_price),
+            (prices.short_token_price, expected.short_token_price),
+        ] {
+            assert_eq!(price.min, expected.min);
+            assert_eq!(price.max, expected.max);
+        }
+        assert_eq!(
+            prices.index_token_price.max,
+            U256 {
+                low: 2_500_000_000_000_000,
+                high: 0
+            }
+        );
+    }
+
+    #[test]
+    fn test_to_float_market_prices() {
+        // ETH/USD and USDC/USD from Pragma with 8 decimals.
+        let prices = MarketTokenPrices {
+            long_token_price: token_price(2_500_00000000, 8, 18),
+            short_token_price: token_price(1_00000000, 8, 6),
+            index_token_price: None,
+        };
+
+        let float_prices = to_float_market_prices(&prices);
+        // 2500 usd with 30 decimals for 10^18 wei.
+        assert_eq!(
+            float_prices.long_token_price.min,
+            U256 {
+                low: 2_500_000_000_000_000,
+                high: 0
+            }
+        );
+        // 1 usd with 30 decimals for 10^6 units.
+        assert_eq!(
+            float_prices.short_token_price.max,
+            U256 {
+                low: 1_000_000_000_000_000_000_000_000,
+                high: 0
+            }
+        );
+        assert_eq!(float_prices.index_token_price.min, U256 { low: 0, high: 0 });
+
+        let raw_prices = to_raw_market_prices(&prices);
+        assert_eq!(
+            raw_prices.long_token_price.min,
+            U256 {
+                low: 2_500_00000000,
+                high: 0
+            }
+        );
+
+        // A price with more decimals than the 30 of a unit is scaled down.
+        assert_eq!(
+            to_float_price(123_456, -2),
+            U256 {
+                low: 1_234,
+                high: 0
+            }
+        );
+    }
 
     #[tokio::test]
     async fn test_handler_success() {
@@ -180,7 +321,10 @@ mod tests {
             aggregation: "median".to_owned(),
         };
 
-        let price_info = get_pragma_price(path, query).await;
+        let price_info = match pragma_api_key() {
+            Ok(api_key) => get_pragma_price(PRAGMA_API_URL, &api_key, path, query).await,
+            Err(e) => Err(e),
+        };
         match price_info {
             Ok(price_info) => {
                 assert_eq!(price_info.decimals, 8);
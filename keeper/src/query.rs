@@ -1,6 +1,6 @@
 use sqlx::{Error, PgPool};
 
-use crate::types::Market;
+use crate::types::{Market, Position, SatoruAction};
 
 pub async fn get_market(market_token: String, pool: &PgPool) -> Result<Market, Error> {
     let market: Result<Market, Error> =
@@ -10,3 +10,31 @@ pub async fn get_market(market_token: String, pool: &PgPool) -> Result<Market, E
             .await;
     return market;
 }
+
+pub async fn get_order(key: String, pool: &PgPool) -> Result<SatoruAction, Error> {
+    sqlx::query_as("SELECT * FROM orders WHERE key = $1")
+        .bind(key)
+        .fetch_one(pool)
+        .await
+}
+
+pub async fn get_deposit(key: String, pool: &PgPool) -> Result<SatoruAction, Error> {
+    sqlx::query_as("SELECT * FROM deposits WHERE key = $1")
+        .bind(key)
+        .fetch_one(pool)
+        .await
+}
+
+pub async fn get_withdrawal(key: String, pool: &PgPool) -> Result<SatoruAction, Error> {
+    sqlx::query_as("SELECT * FROM withdrawals WHERE key = $1")
+        .bind(key)
+        .fetch_one(pool)
+        .await
+}
+
+pub async fn get_position(key: String, pool: &PgPool) -> Result<Position, Error> {
+    sqlx::query_as("SELECT * FROM positions WHERE key = $1")
+        .bind(key)
+        .fetch_one(pool)
+        .await
+}
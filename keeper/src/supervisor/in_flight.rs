@@ -0,0 +1,87 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use tokio::sync::Notify;
+
+// Counts the actions being handled or submitted, so a shutdown can wait for them.
+#[derive(Debug, Default)]
+pub struct InFlight {
+    count: AtomicUsize,
+    idle: Notify,
+}
+
+// Marks one action as in flight until dropped.
+#[derive(Debug)]
+pub struct InFlightGuard {
+    in_flight: Arc<InFlight>,
+}
+
+impl InFlight {
+    pub fn start(self: &Arc<Self>) -> InFlightGuard {
+        self.count.fetch_add(1, Ordering::SeqCst);
+        InFlightGuard {
+            in_flight: Arc::clone(self),
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.count.load(Ordering::SeqCst)
+    }
+
+    // Returns once no action is in flight.
+    pub async fn wait_idle(&self) {
+        loop {
+            // Registered before the check so a guard dropped in between still wakes us.
+            let idle = self.idle.notified();
+            if self.count() == 0 {
+                return;
+            }
+            idle.await;
+        }
+    }
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        if self.in_flight.count.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.in_flight.idle.notify_waiters();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::time::timeout;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_wait_idle() {
+        let in_flight = Arc::new(InFlight::default());
+        timeout(Duration::from_millis(10), in_flight.wait_idle())
+            .await
+            .expect("Nothing is in flight");
+
+        let first = in_flight.start();
+        let second = in_flight.start();
+        assert_eq!(in_flight.count(), 2);
+
+        let waiter = tokio::spawn({
+            let in_flight = Arc::clone(&in_flight);
+            async move { in_flight.wait_idle().await }
+        });
+        drop(first);
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(!waiter.is_finished());
+
+        drop(second);
+        timeout(Duration::from_millis(100), waiter)
+            .await
+            .expect("Waiter is woken up once idle")
+            .unwrap();
+    }
+}
@@ -0,0 +1,127 @@
+use std::time::Duration;
+
+use log::{info, warn};
+use sqlx::{postgres::PgConnection, Connection};
+use tokio::{
+    sync::watch,
+    time::{sleep, timeout},
+};
+
+use crate::{
+    error::KeeperError,
+    metrics::{KEEPER_LEADER, KEEPER_LEADER_CHANGES},
+};
+
+// How often a standby tries to take the lock and the leader checks its connection.
+const CHECK_INTERVAL: Duration = Duration::from_secs(2);
+// A leader whose connection does not answer within this delay steps down.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Leader,
+    Standby,
+}
+
+// Elects one leader among the keepers sharing a database with a session advisory lock.
+// PostgreSQL releases the lock when the leader's connection closes, so a standby takes
+// over on its next attempt, within a few seconds.
+pub struct LeaderElection {
+    database_url: String,
+    lock_id: i64,
+    role: watch::Sender<Role>,
+}
+
+impl LeaderElection {
+    // @database_url: The database shared by the keepers, the lock uses its own connection.
+    // @lock_id: The advisory lock, the same for every keeper of the election.
+    pub fn new(database_url: impl Into<String>, lock_id: i64) -> Self {
+        let (role, _) = watch::channel(Role::Standby);
+        KEEPER_LEADER.set(0);
+        LeaderElection {
+            database_url: database_url.into(),
+            lock_id,
+            role,
+        }
+    }
+
+    pub fn subscribe(&self) -> watch::Receiver<Role> {
+        self.role.subscribe()
+    }
+
+    pub fn role(&self) -> Role {
+        *self.role.borrow()
+    }
+
+    // Campaigns for the lock and holds it, never returns.
+    // The leader steps down as soon as its connection fails, then campaigns again.
+    pub async fn run(&self) {
+        loop {
+            if let Err(e) = self.campaign().await {
+                warn!(
+                    "Leader election connection failed, retrying in {:?}: {:?}",
+                    CHECK_INTERVAL, e
+                );
+            }
+            self.set_role(Role::Standby);
+            sleep(CHECK_INTERVAL).await;
+        }
+    }
+
+    // Tries to take the lock until it succeeds, then checks the connection holding it.
+    // Returns once the connection fails.
+    async fn campaign(&self) -> Result<(), KeeperError> {
+        let mut connection = with_timeout(PgConnection::connect(&self.database_url)).await?;
+        loop {
+            match self.role() {
+                Role::Standby => {
+                    let locked: bool = with_timeout(
+                        sqlx::query_scalar("SELECT pg_try_advisory_lock($1)")
+                            .bind(self.lock_id)
+                            .fetch_one(&mut connection),
+                    )
+                    .await?;
+                    if locked {
+                        self.set_role(Role::Leader);
+                    }
+                }
+                Role::Leader => with_timeout(connection.ping()).await?,
+            }
+            sleep(CHECK_INTERVAL).await;
+        }
+    }
+
+    fn set_role(&self, role: Role) {
+        let changed = self.role.send_if_modified(|current| {
+            let changed = *current != role;
+            *current = role;
+            changed
+        });
+        if !changed {
+            return;
+        }
+        KEEPER_LEADER_CHANGES.inc();
+        match role {
+            Role::Leader => {
+                KEEPER_LEADER.set(1);
+                info!("Elected leader, running the scanning loops");
+            }
+            Role::Standby => {
+                KEEPER_LEADER.set(0);
+                warn!("Stepped down to standby, stopping the scanning loops");
+            }
+        }
+    }
+}
+
+async fn with_timeout<T>(
+    query: impl std::future::Future<Output = Result<T, sqlx::Error>>,
+) -> Result<T, KeeperError> {
+    match timeout(CHECK_TIMEOUT, query).await {
+        Ok(result) => result.map_err(|e| KeeperError::DatabaseError(e.to_string())),
+        Err(_) => Err(KeeperError::DatabaseError(format!(
+            "No answer within {:?}",
+            CHECK_TIMEOUT
+        ))),
+    }
+}
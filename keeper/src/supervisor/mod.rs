@@ -0,0 +1,3 @@
+pub mod in_flight;
+pub mod leader;
+pub mod process;
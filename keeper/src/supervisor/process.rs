@@ -0,0 +1,224 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use log::{error, info, warn};
+use tokio::{
+    signal,
+    sync::watch,
+    task::{self, JoinSet},
+    time::{sleep, timeout},
+};
+
+use crate::{
+    error::KeeperError,
+    health::serve_health,
+    keeper::{Keeper, KeeperMode},
+    metrics::{KEEPER_LEADER, KEEPER_LOOP_RESTARTS},
+};
+
+use super::leader::{LeaderElection, Role};
+
+// Delay before restarting a loop that stopped, doubled on each restart in a row.
+const MIN_RESTART_DELAY: Duration = Duration::from_secs(1);
+const MAX_RESTART_DELAY: Duration = Duration::from_secs(60);
+// Maximum time given to the loops and the in-flight submissions to finish on shutdown.
+const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(30);
+
+// Runs keeper loops as tasks sharing a single keeper, restarting any loop that fails
+// or panics. On SIGINT or SIGTERM, the loops are asked to stop and the process waits
+// for the queued and in-flight calls to be submitted.
+// With leader election, the liquidation and trigger loops only run on the leader.
+pub struct Supervisor {
+    keeper: Arc<Keeper>,
+    modes: Vec<KeeperMode>,
+    shutdown_timeout: Duration,
+}
+
+impl Supervisor {
+    pub fn new(keeper: Arc<Keeper>, modes: Vec<KeeperMode>) -> Self {
+        Supervisor {
+            keeper,
+            modes,
+            shutdown_timeout: DEFAULT_SHUTDOWN_TIMEOUT,
+        }
+    }
+
+    pub fn with_shutdown_timeout(mut self, shutdown_timeout: Duration) -> Self {
+        self.shutdown_timeout = shutdown_timeout;
+        self
+    }
+
+    // Runs the loops until a shutdown signal is received.
+    // The health endpoint is served alongside them when an address is configured.
+    pub async fn run(self) -> Result<(), KeeperError> {
+        let health_server = match self.keeper.config().health_addr {
+            Some(addr) => {
+                let server = serve_health(addr, Arc::clone(self.keeper.listener_health()))
+                    .map_err(|e| {
+                        KeeperError::ConfigError(format!(
+                            "Could not serve health on {}: {}",
+                            addr, e
+                        ))
+                    })?;
+                let handle = server.handle();
+                task::spawn(server);
+                info!("Serving /health and /metrics on {}", addr);
+                Some(handle)
+            }
+            None => None,
+        };
+
+        let config = self.keeper.config();
+        let election = if config.leader_election && self.modes.iter().any(|mode| is_scanning(*mode))
+        {
+            info!(
+                "Standby, waiting for the leader lock {}",
+                config.leader_lock_id
+            );
+            Some(LeaderElection::new(
+                config.database_url.clone(),
+                config.leader_lock_id,
+            ))
+        } else {
+            KEEPER_LEADER.set(1);
+            None
+        };
+
+        let mut loops = JoinSet::new();
+        for mode in &self.modes {
+            match &election {
+                Some(election) if is_scanning(*mode) => {
+                    loops.spawn(supervise_as_leader(
+                        Arc::clone(&self.keeper),
+                        *mode,
+                        election.subscribe(),
+                    ));
+                }
+                _ => {
+                    loops.spawn(supervise(Arc::clone(&self.keeper), *mode));
+                }
+            }
+        }
+        let campaign = election.map(|election| task::spawn(async move { election.run().await }));
+
+        shutdown_signal().await?;
+        info!("Shutting down, waiting for the keeper loops to stop...");
+        self.keeper.request_shutdown();
+
+        let shutdown = async {
+            while loops.join_next().await.is_some() {}
+            info!("Keeper loops stopped, submitting the queued calls...");
+            self.keeper.drain().await;
+        };
+        let result = timeout(self.shutdown_timeout, shutdown).await;
+        // Closes the lock connection so a standby takes over right away.
+        if let Some(campaign) = campaign {
+            campaign.abort();
+        }
+        if let Some(health_server) = health_server {
+            health_server.stop(true).await;
+        }
+        match result {
+            Ok(()) => {
+                info!("Keeper stopped");
+                Ok(())
+            }
+            Err(_) => Err(KeeperError::ExecutionError(format!(
+                "Shutdown timed out after {:?}, some calls may not have been submitted",
+                self.shutdown_timeout
+            ))),
+        }
+    }
+}
+
+// The loops scanning whole tables, run by a single keeper when electing a leader.
+fn is_scanning(mode: KeeperMode) -> bool {
+    matches!(mode, KeeperMode::Liquidation | KeeperMode::Trigger)
+}
+
+// Runs a keeper loop while this keeper is the leader, stopping it on stepping down.
+async fn supervise_as_leader(
+    keeper: Arc<Keeper>,
+    mode: KeeperMode,
+    mut role: watch::Receiver<Role>,
+) {
+    loop {
+        tokio::select! {
+            elected = wait_for_role(&mut role, Role::Leader) => {
+                if !elected {
+                    return;
+                }
+            }
+            _ = keeper.shutdown_requested() => return,
+        }
+        info!("Starting the {:?} loop as leader", mode);
+        tokio::select! {
+            _ = supervise(Arc::clone(&keeper), mode) => return,
+            _ = wait_for_role(&mut role, Role::Standby) => {
+                warn!("No longer leader, stopped the {:?} loop", mode);
+            }
+        }
+    }
+}
+
+// Returns false if the election stopped before reaching the role.
+async fn wait_for_role(role: &mut watch::Receiver<Role>, wanted: Role) -> bool {
+    role.wait_for(|role| *role == wanted).await.is_ok()
+}
+
+// Runs a keeper loop, restarting it with a backoff until a shutdown is requested.
+async fn supervise(keeper: Arc<Keeper>, mode: KeeperMode) {
+    let mut restart_delay = MIN_RESTART_DELAY;
+    loop {
+        let started_at = Instant::now();
+        // Run in its own task so a panic is reported here instead of ending the supervisor,
+        // the set aborts the task when this future is dropped, e.g. on stepping down.
+        let mut run = JoinSet::new();
+        run.spawn(Arc::clone(&keeper).run_mode(mode));
+        let Some(result) = run.join_next().await else {
+            return;
+        };
+        if keeper.is_shutting_down() {
+            return;
+        }
+        match result {
+            Ok(Ok(())) => warn!("{:?} loop stopped", mode),
+            Ok(Err(e)) => error!("{:?} loop failed: {:?}", mode, e),
+            Err(e) => error!("{:?} loop panicked: {}", mode, e),
+        }
+
+        // A loop that ran for a while before failing starts over from the minimum delay.
+        if started_at.elapsed() > MAX_RESTART_DELAY {
+            restart_delay = MIN_RESTART_DELAY;
+        }
+        info!("Restarting {:?} loop in {:?}", mode, restart_delay);
+        KEEPER_LOOP_RESTARTS.inc();
+        tokio::select! {
+            _ = sleep(restart_delay) => {}
+            _ = keeper.shutdown_requested() => return,
+        }
+        restart_delay = (restart_delay * 2).min(MAX_RESTART_DELAY);
+    }
+}
+
+// Returns once the process receives SIGINT (Ctrl+C) or SIGTERM.
+async fn shutdown_signal() -> Result<(), KeeperError> {
+    #[cfg(unix)]
+    {
+        let mut terminate = signal::unix::signal(signal::unix::SignalKind::terminate())
+            .map_err(|e| KeeperError::ExecutionError(format!("SIGTERM handler: {}", e)))?;
+        tokio::select! {
+            result = signal::ctrl_c() => result
+                .map_err(|e| KeeperError::ExecutionError(format!("SIGINT handler: {}", e))),
+            _ = terminate.recv() => Ok(()),
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        signal::ctrl_c()
+            .await
+            .map_err(|e| KeeperError::ExecutionError(format!("SIGINT handler: {}", e)))
+    }
+}
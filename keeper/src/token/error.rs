@@ -0,0 +1,13 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum TokenError {
+    #[error("Token is not in the registry")]
+    UnknownToken(String),
+    #[error("Could not read token registry file")]
+    RegistryFileError(String),
+    #[error("Token registry is invalid")]
+    InvalidRegistry(String),
+    #[error("Database Error")]
+    DatabaseError(String),
+}
@@ -0,0 +1,157 @@
+use std::{collections::HashMap, fs};
+
+use cainome::cairo_serde::ContractAddress;
+use serde::Deserialize;
+use sqlx::{PgPool, Row};
+use starknet::core::types::FieldElement;
+
+use super::error::TokenError;
+
+// A token the keeper can price.
+// @symbol: The token symbol, only used in logs.
+// @address: The token contract address.
+// @pair_id: The Pragma pair of the token, e.g. ETH/USD.
+// @decimals: The decimals of the token.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TokenInfo {
+    pub symbol: String,
+    pub address: String,
+    pub pair_id: String,
+    pub decimals: u32,
+}
+
+impl TokenInfo {
+    // The base and quote of the Pragma pair, as expected by the Pragma API.
+    pub fn pair(&self) -> (String, String) {
+        match self.pair_id.split_once('/') {
+            Some((base, quote)) => (base.to_lowercase(), quote.to_lowercase()),
+            None => (self.pair_id.to_lowercase(), "usd".to_owned()),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct TokenRegistryFile {
+    tokens: Vec<TokenInfo>,
+}
+
+// Maps token addresses to their Pragma pair and decimals.
+// The keeper loads it once at startup and shares it with its price providers.
+#[derive(Debug, Default)]
+pub struct TokenRegistry {
+    tokens: HashMap<FieldElement, TokenInfo>,
+}
+
+impl TokenRegistry {
+    // Reads a registry from a TOML file with one [[tokens]] entry per token.
+    pub fn load(path: &str) -> Result<Self, TokenError> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| TokenError::RegistryFileError(format!("{}: {}", path, e)))?;
+        Self::from_toml_str(&content)
+    }
+
+    pub fn from_toml_str(content: &str) -> Result<Self, TokenError> {
+        let file: TokenRegistryFile =
+            toml::from_str(content).map_err(|e| TokenError::InvalidRegistry(e.to_string()))?;
+
+        let mut tokens = HashMap::new();
+        for token in file.tokens {
+            let address = FieldElement::from_hex_be(&token.address).map_err(|e| {
+                TokenError::InvalidRegistry(format!("{} address: {}", token.symbol, e))
+            })?;
+            if !token.pair_id.contains('/') {
+                return Err(TokenError::InvalidRegistry(format!(
+                    "{} pair_id {} is not BASE/QUOTE",
+                    token.symbol, token.pair_id
+                )));
+            }
+            if tokens.insert(address, token).is_some() {
+                return Err(TokenError::InvalidRegistry(format!(
+                    "{:#x} is registered twice",
+                    address
+                )));
+            }
+        }
+
+        Ok(TokenRegistry { tokens })
+    }
+
+    pub fn get(&self, token: ContractAddress) -> Result<&TokenInfo, TokenError> {
+        self.tokens
+            .get(&token.0)
+            .ok_or_else(|| TokenError::UnknownToken(format!("{:#x}", token.0)))
+    }
+
+    // Returns the tokens of indexed markets which are not in the registry.
+    // @pool: A reference to a connection pool for PostgreSQL.
+    pub async fn get_unregistered_market_tokens(
+        &self,
+        pool: &PgPool,
+    ) -> Result<Vec<String>, TokenError> {
+        let rows = sqlx::query(
+            "SELECT long_token AS token FROM market_created
+            UNION SELECT short_token FROM market_created
+            UNION SELECT index_token FROM market_created",
+        )
+        .fetch_all(pool)
+        .await
+        .map_err(|e| TokenError::DatabaseError(e.to_string()))?;
+
+        let mut unregistered = Vec::new();
+        for row in rows {
+            let token: Option<String> = row
+                .try_get("token")
+                .map_err(|e| TokenError::DatabaseError(e.to_string()))?;
+            let Some(token) = token else { continue };
+            let registered = match FieldElement::from_hex_be(&token) {
+                // Swap only markets have no index token.
+                Ok(address) => address == FieldElement::ZERO || self.tokens.contains_key(&address),
+                Err(_) => false,
+            };
+            if !registered {
+                unregistered.push(token);
+            }
+        }
+
+        Ok(unregistered)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REGISTRY: &str = r#"
+        [[tokens]]
+        symbol = "ETH"
+        address = "0x049d36570d4e46f48e99674bd3fcc84644ddd6b96f7c741b1562b82f9e004dc7"
+        pair_id = "ETH/USD"
+        decimals = 18
+    "#;
+
+    #[test]
+    fn test_registry_lookup() {
+        let registry = TokenRegistry::from_toml_str(REGISTRY).unwrap();
+        let eth = registry
+            .get(ContractAddress::from(
+                FieldElement::from_hex_be(
+                    "0x49d36570d4e46f48e99674bd3fcc84644ddd6b96f7c741b1562b82f9e004dc7",
+                )
+                .unwrap(),
+            ))
+            .unwrap();
+        assert_eq!(eth.pair(), ("eth".to_owned(), "usd".to_owned()));
+        assert_eq!(eth.decimals, 18);
+
+        let unknown = registry.get(ContractAddress::from(
+            FieldElement::from_hex_be("0x1").unwrap(),
+        ));
+        assert!(matches!(unknown, Err(TokenError::UnknownToken(_))));
+    }
+
+    #[test]
+    fn test_registry_rejects_duplicates() {
+        let registry = TokenRegistry::from_toml_str(&format!("{}{}", REGISTRY, REGISTRY));
+        assert!(matches!(registry, Err(TokenError::InvalidRegistry(_))));
+    }
+}
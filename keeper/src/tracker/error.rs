@@ -0,0 +1,11 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum TrackerError {
+    #[error("Provider Error")]
+    ProviderError(String),
+    #[error("Database Error")]
+    DatabaseError(String),
+    #[error("Unexpected receipt")]
+    UnexpectedReceipt(String),
+}
@@ -0,0 +1,4 @@
+pub mod error;
+pub mod process;
+pub mod receipt;
+pub mod store;
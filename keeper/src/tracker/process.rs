@@ -0,0 +1,115 @@
+use std::sync::Arc;
+
+use log::{error, info, warn};
+use sqlx::PgPool;
+use starknet::{accounts::ConnectedAccount, core::types::FieldElement};
+use tokio::task::{self, JoinHandle};
+
+use crate::nonce::manager::NonceManager;
+
+use super::{
+    receipt::{wait_for_transaction, ExecutionOutcome, ExecutionStatus},
+    store::{record_outcome, record_submission},
+};
+
+// Tracks a sent keeper transaction in the background until it leaves the pending state.
+// The submission and its outcome are stored in the keeper_executions table, the
+// submission is stored before returning so that a process exiting right after, like the
+// execute command, still leaves its pending row for the catch-up.
+// @nonce_manager: The keeper account, resynced when the transaction is dropped.
+// @pool: A connection pool for PostgreSQL.
+// @action_key: The key of the executed action.
+// @action_type: The kind of action executed (order, deposit, ...).
+// @transaction_hash: The hash of the sent transaction.
+pub async fn spawn_tracking(
+    nonce_manager: Arc<NonceManager>,
+    pool: PgPool,
+    action_key: String,
+    action_type: &'static str,
+    transaction_hash: FieldElement,
+) -> JoinHandle<()> {
+    let actions = [(action_key, action_type)];
+    record_submissions(&pool, &actions, transaction_hash).await;
+    task::spawn(async move {
+        wait_for_outcome(&nonce_manager, &pool, &actions, transaction_hash).await;
+    })
+}
+
+// Tracks a transaction executing one or several actions and waits for its outcome.
+// Every action of the transaction gets its own row in the keeper_executions table.
+// A dropped or rejected transaction leaves a gap in the nonces, the local nonce is
+// then read again from chain so the next submissions are not stuck behind it.
+// @nonce_manager: The keeper account, used for its provider and nonce.
+// @pool: A reference to a connection pool for PostgreSQL.
+// @actions: The key and kind of every action executed by the transaction.
+// @transaction_hash: The hash of the sent transaction.
+pub async fn track_execution(
+    nonce_manager: &NonceManager,
+    pool: &PgPool,
+    actions: &[(String, &'static str)],
+    transaction_hash: FieldElement,
+) -> Option<ExecutionOutcome> {
+    record_submissions(pool, actions, transaction_hash).await;
+    wait_for_outcome(nonce_manager, pool, actions, transaction_hash).await
+}
+
+async fn record_submissions(
+    pool: &PgPool,
+    actions: &[(String, &'static str)],
+    transaction_hash: FieldElement,
+) {
+    for (action_key, action_type) in actions {
+        if let Err(e) = record_submission(pool, action_key, action_type, transaction_hash).await {
+            error!("Failed to record {} {}: {:?}", action_type, action_key, e);
+        }
+    }
+}
+
+async fn wait_for_outcome(
+    nonce_manager: &NonceManager,
+    pool: &PgPool,
+    actions: &[(String, &'static str)],
+    transaction_hash: FieldElement,
+) -> Option<ExecutionOutcome> {
+    let account = nonce_manager.account();
+    let outcome = match wait_for_transaction(account.provider(), transaction_hash).await {
+        Ok(outcome) => outcome,
+        Err(e) => {
+            error!(
+                "Failed to track transaction {:#x}: {:?}",
+                transaction_hash, e
+            );
+            return None;
+        }
+    };
+    if outcome.status == ExecutionStatus::Dropped {
+        warn!(
+            "Transaction {:#x} dropped, resyncing the nonce from chain",
+            transaction_hash
+        );
+        nonce_manager.resync().await;
+    }
+
+    for (action_key, action_type) in actions {
+        match outcome.status {
+            ExecutionStatus::Accepted => info!(
+                "{} {} executed in transaction {:#x}",
+                action_type, action_key, transaction_hash
+            ),
+            _ => warn!(
+                "{} {} not executed, transaction {:#x} {}: {:?}",
+                action_type,
+                action_key,
+                transaction_hash,
+                outcome.status.as_str(),
+                outcome.revert_reason
+            ),
+        }
+
+        if let Err(e) = record_outcome(pool, action_key, transaction_hash, &outcome).await {
+            error!("Failed to record outcome of {}: {:?}", action_key, e);
+        }
+    }
+
+    Some(outcome)
+}
@@ -0,0 +1,133 @@
+use std::time::Duration;
+
+use log::warn;
+use starknet::{
+    core::types::{
+        ExecutionResult, FieldElement, StarknetError, TransactionReceipt, TransactionStatus,
+    },
+    providers::{jsonrpc::HttpTransport, JsonRpcClient, Provider, ProviderError},
+};
+use tokio::time::sleep;
+
+use super::error::TrackerError;
+
+// Delay between two status polls of the same transaction.
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+// Number of polls the node may not know the transaction before it is considered dropped.
+const MAX_NOT_FOUND_POLLS: u32 = 24;
+// Number of consecutive RPC failures tolerated before giving up on a transaction.
+const MAX_PROVIDER_ERRORS: u32 = 10;
+
+// The lifecycle state of a keeper transaction.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExecutionStatus {
+    Pending,
+    Accepted,
+    Reverted,
+    Dropped,
+    // Given up before any transaction went through.
+    Failed,
+}
+
+impl ExecutionStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExecutionStatus::Pending => "pending",
+            ExecutionStatus::Accepted => "accepted",
+            ExecutionStatus::Reverted => "reverted",
+            ExecutionStatus::Dropped => "dropped",
+            ExecutionStatus::Failed => "failed",
+        }
+    }
+}
+
+// The final result of a keeper transaction once it left the pending state.
+// @status: Accepted, Reverted or Dropped.
+// @revert_reason: The reason returned by the sequencer when the execution reverted.
+// @actual_fee: The fee actually paid by the keeper account, when known.
+#[derive(Debug, Clone)]
+pub struct ExecutionOutcome {
+    pub status: ExecutionStatus,
+    pub revert_reason: Option<String>,
+    pub actual_fee: Option<FieldElement>,
+}
+
+impl ExecutionOutcome {
+    fn dropped() -> Self {
+        ExecutionOutcome {
+            status: ExecutionStatus::Dropped,
+            revert_reason: None,
+            actual_fee: None,
+        }
+    }
+}
+
+// Polls the status of a transaction until it is accepted, reverted or dropped.
+// @provider: The provider used to query the transaction status and receipt.
+// @transaction_hash: The hash returned when the transaction was sent.
+pub async fn wait_for_transaction(
+    provider: &JsonRpcClient<HttpTransport>,
+    transaction_hash: FieldElement,
+) -> Result<ExecutionOutcome, TrackerError> {
+    let mut not_found_polls = 0;
+    let mut provider_errors = 0;
+    loop {
+        match provider.get_transaction_status(transaction_hash).await {
+            Ok(TransactionStatus::Received) => {
+                provider_errors = 0;
+            }
+            Ok(TransactionStatus::Rejected) => return Ok(ExecutionOutcome::dropped()),
+            Ok(TransactionStatus::AcceptedOnL2(_)) | Ok(TransactionStatus::AcceptedOnL1(_)) => {
+                return get_receipt_outcome(provider, transaction_hash).await;
+            }
+            Err(ProviderError::StarknetError(StarknetError::TransactionHashNotFound)) => {
+                not_found_polls += 1;
+                if not_found_polls >= MAX_NOT_FOUND_POLLS {
+                    return Ok(ExecutionOutcome::dropped());
+                }
+            }
+            Err(e) => {
+                provider_errors += 1;
+                if provider_errors >= MAX_PROVIDER_ERRORS {
+                    return Err(TrackerError::ProviderError(format!(
+                        "Could not get status of {:#x}: {}",
+                        transaction_hash, e
+                    )));
+                }
+                warn!(
+                    "Failed to get status of transaction {:#x}: {:?}",
+                    transaction_hash, e
+                );
+            }
+        }
+        sleep(POLL_INTERVAL).await;
+    }
+}
+
+async fn get_receipt_outcome(
+    provider: &JsonRpcClient<HttpTransport>,
+    transaction_hash: FieldElement,
+) -> Result<ExecutionOutcome, TrackerError> {
+    let receipt = provider
+        .get_transaction_receipt(transaction_hash)
+        .await
+        .map_err(|e| TrackerError::ProviderError(format!("Could not get receipt: {}", e)))?;
+
+    match receipt.receipt {
+        TransactionReceipt::Invoke(invoke_receipt) => {
+            let (status, revert_reason) = match invoke_receipt.execution_result {
+                ExecutionResult::Succeeded => (ExecutionStatus::Accepted, None),
+                ExecutionResult::Reverted { reason } => (ExecutionStatus::Reverted, Some(reason)),
+            };
+            Ok(ExecutionOutcome {
+                status,
+                revert_reason,
+                actual_fee: Some(invoke_receipt.actual_fee.amount),
+            })
+        }
+        _ => Err(TrackerError::UnexpectedReceipt(format!(
+            "{:#x} is not an invoke transaction",
+            transaction_hash
+        ))),
+    }
+}
@@ -0,0 +1,118 @@
+use sqlx::PgPool;
+use starknet::core::types::FieldElement;
+
+use super::{
+    error::TrackerError,
+    receipt::{ExecutionOutcome, ExecutionStatus},
+};
+
+// Records a freshly sent keeper transaction as pending.
+// A new submission for the same action key replaces the previous one.
+// @pool: A reference to a connection pool for PostgreSQL.
+// @action_key: The key of the executed order, deposit, withdrawal or position.
+// @action_type: The kind of action executed (order, deposit, ...).
+// @transaction_hash: The hash of the sent transaction.
+pub async fn record_submission(
+    pool: &PgPool,
+    action_key: &str,
+    action_type: &str,
+    transaction_hash: FieldElement,
+) -> Result<(), TrackerError> {
+    sqlx::query(
+        "INSERT INTO keeper_executions (action_key, action_type, transaction_hash, status)
+         VALUES ($1, $2, $3, $4)
+         ON CONFLICT (action_key) DO UPDATE SET
+            action_type = EXCLUDED.action_type,
+            transaction_hash = EXCLUDED.transaction_hash,
+            status = EXCLUDED.status,
+            revert_reason = NULL,
+            actual_fee = NULL,
+            error = NULL,
+            submitted_at = NOW(),
+            updated_at = NOW()",
+    )
+    .bind(action_key)
+    .bind(action_type)
+    .bind(format!("{:#x}", transaction_hash))
+    .bind(ExecutionStatus::Pending.as_str())
+    .execute(pool)
+    .await
+    .map_err(|e| TrackerError::DatabaseError(format!("Could not record submission: {}", e)))?;
+    Ok(())
+}
+
+// Stores the final outcome of a keeper transaction.
+// @pool: A reference to a connection pool for PostgreSQL.
+// @action_key: The key of the executed action.
+// @transaction_hash: The hash of the tracked transaction.
+// @outcome: The outcome returned by the receipt polling.
+pub async fn record_outcome(
+    pool: &PgPool,
+    action_key: &str,
+    transaction_hash: FieldElement,
+    outcome: &ExecutionOutcome,
+) -> Result<(), TrackerError> {
+    sqlx::query(
+        "UPDATE keeper_executions
+         SET status = $3, revert_reason = $4, actual_fee = CAST($5 AS NUMERIC), updated_at = NOW()
+         WHERE action_key = $1 AND transaction_hash = $2",
+    )
+    .bind(action_key)
+    .bind(format!("{:#x}", transaction_hash))
+    .bind(outcome.status.as_str())
+    .bind(outcome.revert_reason.clone())
+    .bind(outcome.actual_fee.map(|fee| fee.to_string()))
+    .execute(pool)
+    .await
+    .map_err(|e| TrackerError::DatabaseError(format!("Could not record outcome: {}", e)))?;
+    Ok(())
+}
+
+// Marks an action as failed once its submission was given up, no transaction is tracked.
+// @pool: A reference to a connection pool for PostgreSQL.
+// @action_key: The key of the action.
+// @action_type: The kind of action (order, deposit, ...).
+// @error: The last submission error.
+pub async fn record_failure(
+    pool: &PgPool,
+    action_key: &str,
+    action_type: &str,
+    error: &str,
+) -> Result<(), TrackerError> {
+    sqlx::query(
+        "INSERT INTO keeper_executions (action_key, action_type, status, error)
+         VALUES ($1, $2, $3, $4)
+         ON CONFLICT (action_key) DO UPDATE SET
+            action_type = EXCLUDED.action_type,
+            transaction_hash = NULL,
+            status = EXCLUDED.status,
+            revert_reason = NULL,
+            actual_fee = NULL,
+            error = EXCLUDED.error,
+            updated_at = NOW()",
+    )
+    .bind(action_key)
+    .bind(action_type)
+    .bind(ExecutionStatus::Failed.as_str())
+    .bind(error)
+    .execute(pool)
+    .await
+    .map_err(|e| TrackerError::DatabaseError(format!("Could not record failure: {}", e)))?;
+    Ok(())
+}
+
+// Whether the latest keeper transaction of an action is still pending or was accepted.
+// @pool: A reference to a connection pool for PostgreSQL.
+// @action_key: The key of the action.
+pub async fn has_live_execution(pool: &PgPool, action_key: &str) -> Result<bool, TrackerError> {
+    let live: Option<bool> = sqlx::query_scalar(
+        "SELECT status IN ($2, $3) FROM keeper_executions WHERE action_key = $1",
+    )
+    .bind(action_key)
+    .bind(ExecutionStatus::Pending.as_str())
+    .bind(ExecutionStatus::Accepted.as_str())
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| TrackerError::DatabaseError(format!("Could not get execution: {}", e)))?;
+    Ok(live.unwrap_or(false))
+}
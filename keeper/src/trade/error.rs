@@ -10,4 +10,6 @@ pub enum TradeError {
     SmartContractError(String),
     #[error("Pragma API error")]
     PragmaAPIError(String),
+    #[error("Oracle params error")]
+    OracleParamsError(String),
 }
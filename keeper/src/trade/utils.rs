@@ -1,128 +1,177 @@
-use std::{env, sync::Arc};
+use std::sync::Arc;
 
-use cainome::cairo_serde::{ContractAddress, U256};
+use cainome::cairo_serde::ContractAddress;
 use starknet::{
-    accounts::{Call, SingleOwnerAccount},
-    core::types::FieldElement,
-    providers::jsonrpc::{HttpTransport, JsonRpcClient},
+    accounts::{ConnectedAccount, SingleOwnerAccount},
+    core::types::{BlockId, BlockTag, FieldElement, MaybePendingBlockWithTxHashes},
+    providers::{jsonrpc::HttpTransport, JsonRpcClient, Provider},
     signers::LocalWallet,
 };
 
 use crate::{
-    price::utils::{get_pragma_price, PathParams, QueryParams},
-    types::{DataStore, Market, Oracle, Price, SatoruAction},
+    config::ContractAddresses,
+    price::{
+        oracle_params::{build_oracle_params, OracleParams, TokenOraclePrice},
+        provider::PriceProvider,
+        utils::get_market_token_prices,
+    },
+    types::{DataStore, Market},
 };
 
 use super::error::TradeError;
 
-pub fn get_token_name_from_address(token_address: ContractAddress) -> String {
-    match token_address {
-        x if x
-            == ContractAddress::from(
-                FieldElement::from_hex_be(
-                    "0x049d36570d4e46f48e99674bd3fcc84644ddd6b96f7c741b1562b82f9e004dc7",
-                )
-                .expect("Cannot convert string to felt"),
-            ) =>
-        {
-            "eth".to_owned()
-        }
-        x if x
-            == ContractAddress::from(
-                FieldElement::from_hex_be(
-                    "0x053c91253bc9682c04929ca02ed00b3e423f6710d2ee7e0d5ebb06f3ecf368a8",
-                )
-                .expect("Cannot convert string to felt"),
-            ) =>
-        {
-            "usdc".to_owned()
-        }
-        _ => "eth".to_owned(), // TODO throw error on this
+// Returns the number and timestamp of the latest accepted block.
+pub async fn get_latest_block_info(
+    account: Arc<SingleOwnerAccount<JsonRpcClient<HttpTransport>, LocalWallet>>,
+) -> Result<(u64, u64), TradeError> {
+    let block = account
+        .provider()
+        .get_block_with_tx_hashes(BlockId::Tag(BlockTag::Latest))
+        .await
+        .map_err(|e| {
+            TradeError::SmartContractError(format!("Could not fetch latest block: {}", e))
+        })?;
+    match block {
+        MaybePendingBlockWithTxHashes::Block(block) => Ok((block.block_number, block.timestamp)),
+        MaybePendingBlockWithTxHashes::PendingBlock(_) => Err(TradeError::SmartContractError(
+            "Latest block is still pending".to_owned(),
+        )),
     }
 }
 
-pub async fn get_set_primary_price_call(
-    trade: SatoruAction,
-    account: Arc<SingleOwnerAccount<JsonRpcClient<HttpTransport>, LocalWallet>>,
-) -> Result<Call, TradeError> {
-    let data_store_address =
-        env::var("DATA_STORE").map_err(|_e| TradeError::EnvVarNotSet("DATA_STORE".to_owned()))?;
-    let data_store = DataStore::new(
-        FieldElement::from_hex_be(&data_store_address)
-            .map_err(|_e| TradeError::ConversionError("data_store_address".to_owned()))?,
-        account.clone(),
-    );
-
-    let oracle_address =
-        env::var("ORACLE").map_err(|_e| TradeError::EnvVarNotSet("ORACLE".to_owned()))?;
-    let oracle = Oracle::new(
-        FieldElement::from_hex_be(&oracle_address)
-            .map_err(|_e| TradeError::ConversionError("oracle_address".to_owned()))?,
-        account.clone(),
-    );
-
-    let market_key_felt = FieldElement::from_hex_be(&trade.market)
-        .map_err(|e| TradeError::ConversionError(format!("trade.key: {}", e)))?;
-
-    let market_datastore = data_store
-        .get_market(&ContractAddress::from(market_key_felt))
-        .call()
-        .await
-        .map_err(|e| TradeError::SmartContractError(format!("Could not get market: {}", e)))?;
-    let market: Market = Market {
-        // TODO optimize
-        long_token: market_datastore.long_token,
-        market_token: market_datastore.market_token,
-        index_token: market_datastore.index_token,
-        short_token: market_datastore.short_token,
-    };
-    let price = price_setup(trade.time_stamp, market.clone()).await?;
-
-    Ok(oracle.set_primary_price_getcall(
-        &market.long_token,
-        &Price {
-            min: price,
-            max: price,
-        },
-    ))
+// Parses a swap path as stored in the database, a comma separated list of market addresses.
+// @swap_path: The swap path column of an order, deposit or withdrawal.
+pub fn parse_swap_path(swap_path: &str) -> Result<Vec<ContractAddress>, TradeError> {
+    swap_path
+        .trim_matches(|c: char| c == '[' || c == ']' || c == '{' || c == '}')
+        .split(',')
+        .map(|market| market.trim())
+        .filter(|market| !market.is_empty())
+        .map(|market| {
+            FieldElement::from_hex_be(market)
+                .map(ContractAddress::from)
+                .map_err(|e| {
+                    TradeError::ConversionError(format!("swap path market {}: {}", market, e))
+                })
+        })
+        .collect()
 }
 
-pub async fn price_setup(timestamp: String, market: Market) -> Result<U256, TradeError> {
-    let path = PathParams {
-        base: get_token_name_from_address(market.long_token).to_owned(),
-        quote: "usd".to_owned(),
-        timestamp,
-        interval: "1min".to_owned(),
+// Returns every market of a swap path.
+// @account: The keeper account, used to read the markets from the DataStore.
+// @contracts: The Satoru contract addresses.
+// @swap_path: The swap path column of an order, deposit or withdrawal, if any.
+pub async fn get_swap_path_markets(
+    account: Arc<SingleOwnerAccount<JsonRpcClient<HttpTransport>, LocalWallet>>,
+    contracts: &ContractAddresses,
+    swap_path: Option<&str>,
+) -> Result<Vec<Market>, TradeError> {
+    let market_keys = match swap_path {
+        Some(swap_path) => parse_swap_path(swap_path)?,
+        None => return Ok(vec![]),
     };
+    if market_keys.is_empty() {
+        return Ok(vec![]);
+    }
 
-    let query = QueryParams {
-        routing: false,
-        aggregation: "median".to_owned(),
-    };
+    let data_store = DataStore::new(contracts.data_store, account);
+
+    let mut markets: Vec<Market> = Vec::new();
+    for market_key in market_keys {
+        let market_datastore = data_store
+            .get_market(&market_key)
+            .call()
+            .await
+            .map_err(|e| {
+                TradeError::SmartContractError(format!("Could not get swap path market: {}", e))
+            })?;
+        markets.push(Market {
+            long_token: market_datastore.long_token,
+            market_token: market_datastore.market_token,
+            index_token: market_datastore.index_token,
+            short_token: market_datastore.short_token,
+        });
+    }
 
-    let price_info = get_pragma_price(path, query)
-        .await
-        .map_err(|e| TradeError::PragmaAPIError(format!("Price did not get returned: {}", e)))?;
+    Ok(markets)
+}
 
-    let price_uint = u128::from_str_radix(price_info.price.as_str().trim_start_matches("0x"), 16)
-        .map_err(|_e| {
-        TradeError::ConversionError("Could not convert hex price to uint".to_owned())
-    })?;
+// Fetches the long, short and index token prices of every market and builds the oracle
+// params of an execution. The prices are reported for the latest block.
+// @account: The keeper account, used to fetch the latest block.
+// @price_provider: The source of the token prices.
+// @timestamp: The timestamp to fetch prices at, the latest block timestamp if None.
+// @markets: The markets to set prices for, a token shared by several markets is priced once.
+pub async fn oracle_params_setup(
+    account: Arc<SingleOwnerAccount<JsonRpcClient<HttpTransport>, LocalWallet>>,
+    price_provider: &dyn PriceProvider,
+    timestamp: Option<String>,
+    markets: Vec<Market>,
+) -> Result<OracleParams, TradeError> {
+    let (block_number, block_timestamp) = get_latest_block_info(account).await?;
+    let price_timestamp = timestamp.unwrap_or_else(|| block_timestamp.to_string());
+
+    let mut prices: Vec<TokenOraclePrice> = Vec::new();
+    for market in markets {
+        let is_priced = |token: &ContractAddress| prices.iter().any(|price| price.token == *token);
+        let index_token_priced = market.index_token == ContractAddress::from(FieldElement::ZERO)
+            || is_priced(&market.index_token);
+        if is_priced(&market.long_token) && is_priced(&market.short_token) && index_token_priced {
+            continue;
+        }
+
+        let market_prices =
+            get_market_token_prices(price_provider, &market, price_timestamp.clone())
+                .await
+                .map_err(|e| {
+                    TradeError::PragmaAPIError(format!("Market prices did not get returned: {}", e))
+                })?;
+        for price in [
+            Some(market_prices.long_token_price),
+            Some(market_prices.short_token_price),
+            market_prices.index_token_price,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            if !prices.iter().any(|priced| priced.token == price.token) {
+                prices.push(price);
+            }
+        }
+    }
 
-    Ok(U256 {
-        low: price_uint,
-        high: 0,
-    })
+    build_oracle_params(&prices, block_number, block_timestamp)
+        .map_err(|e| TradeError::OracleParamsError(format!("{:?}", e)))
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::price::error::PragmaAPIError;
+    use std::{env, sync::Arc};
+
+    use crate::{
+        price::{error::PragmaAPIError, pragma::PragmaPriceProvider},
+        token::registry::TokenRegistry,
+    };
 
     use super::*;
 
+    #[test]
+    fn test_parse_swap_path() {
+        let swap_path = parse_swap_path("0x111, 0x222").unwrap();
+        assert_eq!(
+            swap_path,
+            vec![
+                ContractAddress::from(FieldElement::from_hex_be("0x111").unwrap()),
+                ContractAddress::from(FieldElement::from_hex_be("0x222").unwrap()),
+            ],
+            "Swap path is not parsed well."
+        );
+        assert!(parse_swap_path("").unwrap().is_empty());
+        assert!(parse_swap_path("0x111,market").is_err());
+    }
+
     #[tokio::test]
-    async fn test_price_setup() {
+    async fn test_market_token_prices() {
         let api_key = env::var("PRAGMA_API_KEY").or_else(|_e| Err(PragmaAPIError::APIKeyNotSet()));
         match api_key {
             Ok(_) => {
@@ -147,9 +196,18 @@ mod tests {
                     ),
                 };
 
-                let price = price_setup("1711110660".to_owned(), market).await.unwrap();
+                let prices = get_market_token_prices(
+                    &PragmaPriceProvider::new(Arc::new(
+                        TokenRegistry::load("./resources/tokens.toml").unwrap(),
+                    )),
+                    &market,
+                    "1711110660".to_owned(),
+                )
+                .await
+                .unwrap();
 
-                assert!(price > U256 { low: 3000, high: 0 })
+                assert!(prices.long_token_price.max_price > 3000);
+                assert!(prices.index_token_price.is_none());
             }
             Err(_) => {}
         }
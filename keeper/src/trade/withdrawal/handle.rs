@@ -1,19 +1,20 @@
-use std::{env, sync::Arc, vec};
+use std::{sync::Arc, vec};
 
-use cainome::{
-    cairo_serde::{ContractAddress, U256},
-    rs::abigen,
-};
+use cainome::{cairo_serde::ContractAddress, rs::abigen};
 use log::error;
 use starknet::{
-    accounts::{Account, Call, SingleOwnerAccount},
+    accounts::{Call, SingleOwnerAccount},
     core::types::FieldElement,
     providers::jsonrpc::{HttpTransport, JsonRpcClient},
     signers::LocalWallet,
 };
 
 use crate::{
-    trade::utils::price_setup,
+    batch::executor::PendingCall,
+    config::ContractAddresses,
+    keeper::Keeper,
+    price::provider::PriceProvider,
+    trade::utils::{get_swap_path_markets, oracle_params_setup},
     types::{DataStore, Market, SatoruAction},
 };
 
@@ -24,50 +25,49 @@ abigen!(
     "./resources/satoru_WithdrawalHandler.contract_class.json",
 );
 
-pub async fn handle_withdrawal(
-    account: Arc<SingleOwnerAccount<JsonRpcClient<HttpTransport>, LocalWallet>>,
-    withdrawal: SatoruAction,
-) {
-    match get_execute_withdrawal_call(withdrawal, account.clone()).await {
-        Ok(execute_withdrawal_call) => {
-            let withdrawal_execution_multicall =
-                account.execute(vec![execute_withdrawal_call]).send().await;
+crate::impl_from_oracle_params!(SetPricesParams);
 
-            match withdrawal_execution_multicall {
-                Ok(multicall) => {
-                    // TODO: poll transaction status
-                }
-                Err(e) => {
-                    error!("Withdrawal execution multicall failed: {:?}", e);
-                }
-            }
+// Builds the execute call of a new withdrawal and queues it for the next batch,
+// unless another keeper claimed it.
+pub async fn handle_withdrawal(keeper: &Keeper, withdrawal: SatoruAction) {
+    let withdrawal_key = withdrawal.key.clone();
+    let execution_fee = withdrawal.execution_fee;
+    if !keeper.claim("withdrawal", &withdrawal_key).await {
+        return;
+    }
+    match get_execute_withdrawal_call(
+        withdrawal,
+        keeper.account(),
+        keeper.contracts(),
+        keeper.price_provider().as_ref(),
+    )
+    .await
+    {
+        Ok(execute_withdrawal_call) => {
+            keeper
+                .enqueue(PendingCall {
+                    action_key: withdrawal_key,
+                    action_type: "withdrawal",
+                    call: execute_withdrawal_call,
+                    execution_fee: Some(execution_fee),
+                })
+                .await;
         }
         Err(e) => {
             error!("Failed to get execute withdrawal call: {:?}", e);
+            keeper.release(&withdrawal_key).await;
         }
     }
 }
 
-async fn get_execute_withdrawal_call(
+pub async fn get_execute_withdrawal_call(
     withdrawal: SatoruAction,
     account: Arc<SingleOwnerAccount<JsonRpcClient<HttpTransport>, LocalWallet>>,
+    contracts: &ContractAddresses,
+    price_provider: &dyn PriceProvider,
 ) -> Result<Call, WithdrawalError> {
-    let withdrawal_handler_address = env::var("WITHDRAWAL_HANDLER")
-        .map_err(|_e| WithdrawalError::EnvVarNotSet("WITHDRAWAL_HANDLER".to_owned()))?;
-    let withdrawal_handler = WithdrawalHandler::new(
-        FieldElement::from_hex_be(&withdrawal_handler_address).map_err(|e| {
-            WithdrawalError::ConversionError(format!("withdrawal_handler_address: {}", e))
-        })?,
-        account.clone(),
-    );
-
-    let data_store_address = env::var("DATA_STORE")
-        .map_err(|_e| WithdrawalError::EnvVarNotSet("DATA_STORE".to_owned()))?;
-
-    let data_store_felt = FieldElement::from_hex_be(&data_store_address)
-        .map_err(|e| WithdrawalError::ConversionError(format!("data_store_address: {}", e)))?;
-
-    let data_store = DataStore::new(data_store_felt, account.clone());
+    let withdrawal_handler = WithdrawalHandler::new(contracts.withdrawal_handler, account.clone());
+    let data_store = DataStore::new(contracts.data_store, account.clone());
 
     let market_key_felt = FieldElement::from_hex_be(&withdrawal.market)
         .map_err(|e| WithdrawalError::ConversionError(format!("withdrawal.key: {}", e)))?;
@@ -84,44 +84,27 @@ async fn get_execute_withdrawal_call(
         index_token: market_datastore.index_token,
         short_token: market_datastore.short_token,
     };
-    let price = price_setup(withdrawal.time_stamp, market.clone())
-        .await
-        .map_err(|e| WithdrawalError::PriceError(e.to_string()))?;
+    let mut markets: Vec<Market> = vec![market];
+    for swap_path in [
+        withdrawal.long_token_swap_path.as_deref(),
+        withdrawal.short_token_swap_path.as_deref(),
+    ] {
+        markets.extend(
+            get_swap_path_markets(account.clone(), contracts, swap_path)
+                .await
+                .map_err(|e| WithdrawalError::PriceError(format!("{:?}", e)))?,
+        );
+    }
 
-    let set_prices_params: SetPricesParams = SetPricesParams {
-        signer_info: U256 { low: 1, high: 0 },
-        tokens: vec![market.long_token, market.short_token],
-        compacted_min_oracle_block_numbers: vec![63970, 63970],
-        compacted_max_oracle_block_numbers: vec![64901, 64901],
-        compacted_oracle_timestamps: vec![171119803, 10],
-        compacted_decimals: vec![U256 { low: 1, high: 0 }, U256 { low: 1, high: 0 }],
-        compacted_min_prices: vec![U256 {
-            low: 2147483648010000,
-            high: 0,
-        }],
-        compacted_min_prices_indexes: vec![U256 { low: 0, high: 0 }],
-        compacted_max_prices: vec![price, U256 { low: 1, high: 0 }], // TODO replace 1 by real short token price
-        compacted_max_prices_indexes: vec![U256 { low: 0, high: 0 }],
-        signatures: vec![
-            vec![
-                FieldElement::from_hex_be("0x").map_err(|_e| {
-                    WithdrawalError::ConversionError("Cannot convert string to felt".to_owned())
-                })?,
-                FieldElement::from_hex_be("0x").map_err(|_e| {
-                    WithdrawalError::ConversionError("Cannot convert string to felt".to_owned())
-                })?,
-            ],
-            vec![
-                FieldElement::from_hex_be("0x").map_err(|_e| {
-                    WithdrawalError::ConversionError("Cannot convert string to felt".to_owned())
-                })?,
-                FieldElement::from_hex_be("0x").map_err(|_e| {
-                    WithdrawalError::ConversionError("Cannot convert string to felt".to_owned())
-                })?,
-            ],
-        ],
-        price_feed_tokens: vec![],
-    };
+    let set_prices_params: SetPricesParams = oracle_params_setup(
+        account.clone(),
+        price_provider,
+        Some(withdrawal.time_stamp),
+        markets,
+    )
+    .await
+    .map_err(|e| WithdrawalError::PriceError(format!("{:?}", e)))?
+    .into();
 
     Ok(withdrawal_handler.execute_withdrawal_getcall(
         &FieldElement::from_hex_be(&withdrawal.key).map_err(|_e| {
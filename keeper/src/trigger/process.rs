@@ -10,7 +10,7 @@ use starknet::{
 };
 
 use crate::{
-    price::utils::get_market_prices,
+    price::{provider::PriceProvider, utils::get_market_prices},
     query::get_market,
     types::{MarketPrices, SatoruAction},
 };
@@ -18,6 +18,7 @@ use crate::{
 pub async fn get_triggerable_orders(
     pool: &PgPool,
     account: Arc<SingleOwnerAccount<JsonRpcClient<HttpTransport>, LocalWallet>>,
+    price_provider: &dyn PriceProvider,
 ) -> Result<Vec<SatoruAction>, sqlx::Error> {
     let orders: Vec<SatoruAction> = sqlx::query_as("SELECT * FROM orders")
         .fetch_all(pool)
@@ -45,7 +46,7 @@ pub async fn get_triggerable_orders(
                 .await
                 .expect("Could not get market");
             let market_prices: MarketPrices =
-                get_market_prices(market.clone(), timestamp.to_string())
+                get_market_prices(price_provider, market.clone(), timestamp.to_string())
                     .await
                     .expect(
                         format!("Could not get market prices for market: :{:?}", market).as_str(),
@@ -79,9 +80,7 @@ pub fn should_trigger(order: SatoruAction, market_prices: MarketPrices) -> bool
     let min_price = market_prices.index_token_price.min;
     let max_price = market_prices.index_token_price.max;
     match order.order_type.unwrap().as_str() {
-        "LimitSwap" => {
-            trigger_price >= max_price
-        }
+        "LimitSwap" => trigger_price >= max_price,
         "LimitIncrease" => {
             if is_long {
                 max_price <= trigger_price
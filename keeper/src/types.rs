@@ -45,6 +45,7 @@ abigen!(Reader, "./resources/satoru_Reader.contract_class.json",
 pub enum ActionType {
     INSERT,
     UPDATE,
+    DELETE,
 }
 
 // A struct that contains all fields of differents user actions (Order, Deposit, Withdrawal).
@@ -665,6 +666,34 @@ mod tests {
         assert_eq!(action.min_short_token_amount, Some(15));
     }
 
+    #[test]
+    fn test_deserialize_delete_payload() {
+        let json_data = r#"
+        {
+            "table": "orders",
+            "action_type": "DELETE",
+            "row_data": {
+                "block_number": 100,
+                "time_stamp": "1708839",
+                "transaction_hash": "0x1",
+                "key": "0x2",
+                "account": "0x3",
+                "receiver": "0x4",
+                "callback_contract": "0x5",
+                "ui_fee_receiver": "0x6",
+                "market": "0x7",
+                "execution_fee": 10,
+                "callback_gas_limit": 11,
+                "updated_at_block": 12
+            }
+        }"#;
+
+        let payload: Payload = serde_json::from_str(json_data).unwrap();
+        assert_eq!(payload.table, "orders");
+        assert!(matches!(payload.action_type, ActionType::DELETE));
+        assert_eq!(payload.row_data.key, "0x2");
+    }
+
     #[test]
     fn test_invalid_data() {
         let json_data = r#"
@@ -164,9 +164,49 @@ CREATE TABLE IF NOT EXISTS position (
     is_long BOOLEAN
 );
 
+CREATE TABLE IF NOT EXISTS keeper_executions (
+    action_key TEXT PRIMARY KEY,
+    action_type TEXT NOT NULL,
+    transaction_hash TEXT NOT NULL,
+    status TEXT NOT NULL,
+    revert_reason TEXT,
+    actual_fee NUMERIC,
+    submitted_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Failed actions have no transaction, the error of their last submission is kept instead.
+ALTER TABLE keeper_executions ALTER COLUMN transaction_hash DROP NOT NULL;
+ALTER TABLE keeper_executions ADD COLUMN IF NOT EXISTS error TEXT;
+
+CREATE TABLE IF NOT EXISTS keeper_claims (
+    action_key TEXT PRIMARY KEY,
+    keeper_id TEXT NOT NULL,
+    claimed_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS keeper_dead_letters (
+    id BIGSERIAL PRIMARY KEY,
+    channel TEXT NOT NULL,
+    payload TEXT NOT NULL,
+    error TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS keeper_preflight_reverts (
+    id BIGSERIAL PRIMARY KEY,
+    action_key TEXT NOT NULL,
+    action_type TEXT NOT NULL,
+    revert_reason TEXT NOT NULL,
+    decision TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
 -- Drop the existing function and triggers if it exists
 DROP TRIGGER IF EXISTS orders_notify_update ON orders;
 DROP TRIGGER IF EXISTS orders_notify_insert ON orders;
+DROP TRIGGER IF EXISTS orders_notify_delete ON orders;
 
 DROP FUNCTION IF EXISTS orders_update_notify();
 
@@ -191,9 +231,13 @@ CREATE TRIGGER orders_notify_update AFTER UPDATE ON orders FOR EACH ROW EXECUTE
 -- Add INSERT row trigger
 CREATE TRIGGER orders_notify_insert AFTER INSERT ON orders FOR EACH ROW EXECUTE PROCEDURE orders_update_notify();
 
+-- Add DELETE row trigger
+CREATE TRIGGER orders_notify_delete AFTER DELETE ON orders FOR EACH ROW EXECUTE PROCEDURE orders_update_notify();
+
 -- Drop the existing function and triggers if it exists
 DROP TRIGGER IF EXISTS withdrawals_notify_update ON withdrawals;
 DROP TRIGGER IF EXISTS withdrawals_notify_insert ON withdrawals;
+DROP TRIGGER IF EXISTS withdrawals_notify_delete ON withdrawals;
 
 DROP FUNCTION IF EXISTS withdrawals_update_notify();
 
@@ -218,6 +262,40 @@ CREATE TRIGGER withdrawals_notify_update AFTER UPDATE ON withdrawals FOR EACH RO
 -- Add INSERT row trigger
 CREATE TRIGGER withdrawals_notify_insert AFTER INSERT ON withdrawals FOR EACH ROW EXECUTE PROCEDURE withdrawals_update_notify();
 
+-- Add DELETE row trigger
+CREATE TRIGGER withdrawals_notify_delete AFTER DELETE ON withdrawals FOR EACH ROW EXECUTE PROCEDURE withdrawals_update_notify();
+
+-- Drop the existing function and triggers if it exists
+DROP TRIGGER IF EXISTS deposits_notify_update ON deposits;
+DROP TRIGGER IF EXISTS deposits_notify_insert ON deposits;
+DROP TRIGGER IF EXISTS deposits_notify_delete ON deposits;
+
+DROP FUNCTION IF EXISTS deposits_update_notify();
+
+-- Add a table update notification function
+CREATE OR REPLACE FUNCTION deposits_update_notify() RETURNS trigger AS $$
+DECLARE
+  payload json;
+BEGIN
+  IF TG_OP = 'INSERT' OR TG_OP = 'UPDATE' THEN
+    payload = row_to_json(NEW);
+  ELSE
+    payload = row_to_json(OLD);
+  END IF;
+  PERFORM pg_notify('deposits_update', json_build_object('table', TG_TABLE_NAME, 'action_type', TG_OP, 'row_data', payload)::text);
+  RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+-- Add UPDATE row trigger
+CREATE TRIGGER deposits_notify_update AFTER UPDATE ON deposits FOR EACH ROW EXECUTE PROCEDURE deposits_update_notify();
+
+-- Add INSERT row trigger
+CREATE TRIGGER deposits_notify_insert AFTER INSERT ON deposits FOR EACH ROW EXECUTE PROCEDURE deposits_update_notify();
+
+-- Add DELETE row trigger
+CREATE TRIGGER deposits_notify_delete AFTER DELETE ON deposits FOR EACH ROW EXECUTE PROCEDURE deposits_update_notify();
+
 -- Drop the existing function and triggers if it exists
 DROP TRIGGER IF EXISTS market_created_notify_update ON market_created;
 DROP TRIGGER IF EXISTS market_created_notify_insert ON market_created;